@@ -1,26 +1,24 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use evdev::{EventType, InputEvent, RelativeAxisCode};
-use mouse_scroll_daemon::{AnxiousParams, AnxiousState, apply_anxious_scroll, process_events};
+use mouse_scroll_daemon::{
+    AnxiousParams, AnxiousState, MockClock, ScrollParams, ScrollState, apply_anxious_scroll,
+    process_events,
+};
 use std::hint::black_box;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 
-// Helper function to create InputEvent with specific timestamp
+// Helper function to create InputEvent with specific monotonic timestamp
 // This replicates the internal logic from evdev crate
 fn create_input_event_with_timestamp(
     event_type: EventType,
     code: u16,
     value: i32,
-    timestamp: SystemTime,
+    timestamp: Duration,
 ) -> InputEvent {
-    let (sign, dur) = match timestamp.duration_since(UNIX_EPOCH) {
-        Ok(dur) => (1, dur),
-        Err(e) => (-1, e.duration()),
-    };
-
     let raw = libc::input_event {
         time: libc::timeval {
-            tv_sec: dur.as_secs() as libc::time_t * sign,
-            tv_usec: dur.subsec_micros() as libc::suseconds_t,
+            tv_sec: timestamp.as_secs() as libc::time_t,
+            tv_usec: timestamp.subsec_micros() as libc::suseconds_t,
         },
         type_: event_type.0,
         code,
@@ -29,13 +27,8 @@ fn create_input_event_with_timestamp(
     InputEvent::from(raw)
 }
 
-// Helper function to create AnxiousState with a specific timestamp
-fn create_anxious_state_with_time(prev_time: SystemTime) -> AnxiousState {
-    AnxiousState { prev_time }
-}
-
 fn create_test_events() -> Vec<InputEvent> {
-    let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
+    let base_time = Duration::from_secs(1000000000);
     vec![
         // Chronologically ordered events across all types
         // 0 ms
@@ -157,12 +150,12 @@ fn benchmark_apply_anxious_scroll(c: &mut Criterion) {
     // Simple benchmark of the core function - velocity doesn't affect performance
     group.bench_function("core_function", |b| {
         let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
+        let base_time = Duration::from_secs(1000000000);
         let timestamp = base_time + Duration::from_millis(10);
 
         b.iter(|| {
             // Reset state for each iteration since apply_anxious_scroll mutates it
-            let mut state = create_anxious_state_with_time(base_time);
+            let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
             black_box(apply_anxious_scroll(
                 black_box(-120.0), // Use realistic scroll value
                 black_box(timestamp),
@@ -188,12 +181,12 @@ fn benchmark_event_processing(c: &mut Criterion) {
                 .cycle()
                 .take(size)
                 .collect::<Vec<_>>();
-            let params = AnxiousParams::default();
-            let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
+            let params: ScrollParams = ScrollParams::default();
+            let base_time = Duration::from_secs(1000000000);
 
             b.iter(|| {
                 // Create a state with a timestamp before the events to ensure proper ordering
-                let mut state_clone = create_anxious_state_with_time(base_time);
+                let mut state_clone = ScrollState::with_clock(&MockClock::new(base_time));
 
                 // Use the actual process_events function - this is the real hot path
                 black_box(process_events(
@@ -208,12 +201,12 @@ fn benchmark_event_processing(c: &mut Criterion) {
     // Test realistic event processing with proper timestamps
     group.bench_function("realistic_event_processing", |b| {
         let events = create_test_events();
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams::default();
+        let base_time = Duration::from_secs(1000000000);
 
         b.iter(|| {
             // Create a state with a timestamp before the events to ensure proper ordering
-            let mut state_clone = create_anxious_state_with_time(base_time);
+            let mut state_clone = ScrollState::with_clock(&MockClock::new(base_time));
             // Use the actual process_events function with proper timestamps
             black_box(process_events(
                 black_box(events.iter().cloned()),
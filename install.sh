@@ -82,12 +82,19 @@ fi
 # Create directories if they don't exist
 mkdir -p /usr/local/bin
 mkdir -p /etc/systemd/system
+mkdir -p /etc/anxious-scroll-daemon
 
 # Copy binary
 echo -e "${YELLOW}Installing binary to /usr/local/bin/...${NC}"
 cp target/release/anxious-scroll-daemon /usr/local/bin/
 chmod +x /usr/local/bin/anxious-scroll-daemon
 
+# Install the example configuration, keeping any existing one
+if [ ! -f "/etc/anxious-scroll-daemon/config.toml" ]; then
+    echo -e "${YELLOW}Installing configuration to /etc/anxious-scroll-daemon/...${NC}"
+    cp config.example.toml /etc/anxious-scroll-daemon/config.toml
+fi
+
 # Copy service file
 echo -e "${YELLOW}Installing systemd service...${NC}"
 cp anxious-scroll-daemon.service /etc/systemd/system/
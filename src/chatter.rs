@@ -0,0 +1,287 @@
+use crate::event_time;
+use evdev::{EventType, InputEvent, KeyCode};
+use log::debug;
+use std::time::Duration;
+
+/// How a button's presses are told apart from switch chatter
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChatterThresholds {
+    /// A press this soon after the previous release is a bounce, and is dropped along
+    /// with its release
+    pub min_gap: Duration = Duration::from_millis(30),
+    /// Press and release pairs shorter than this are dropped. Presses are held back
+    /// this long to find out, so keep it short; zero turns the check off.
+    pub min_press: Duration = Duration::ZERO,
+}
+
+/// Parameters for filtering out the phantom clicks of worn switches
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChatterParams {
+    pub enabled: bool = false,
+    /// Thresholds for buttons not listed in `buttons`
+    pub default: ChatterThresholds = ChatterThresholds { .. },
+    /// Thresholds for individual buttons
+    pub buttons: Vec<(KeyCode, ChatterThresholds)> = Vec::new(),
+}
+
+impl ChatterParams {
+    pub fn thresholds(&self, button: KeyCode) -> &ChatterThresholds {
+        self.buttons
+            .iter()
+            .find(|(code, _)| *code == button)
+            .map_or(&self.default, |(_, thresholds)| thresholds)
+    }
+}
+
+/// Chatter filtering state of one button
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ButtonChatter {
+    /// Monotonic time of the last release, passed through or not
+    pub last_release: Option<Duration> = None,
+    /// A bounced press was dropped, so its release and repeats are dropped too
+    pub bouncing: bool = false,
+    /// A press held back until it has lasted `min_press`
+    pub held: Option<InputEvent> = None,
+    /// Presses and releases dropped so far
+    pub suppressed: u64 = 0,
+}
+
+/// Per-button chatter filter for `EV_KEY` events
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChatterFilter {
+    pub buttons: Vec<(KeyCode, ButtonChatter)> = Vec::new(),
+}
+
+impl ChatterFilter {
+    pub const fn new() -> Self {
+        Self {
+            buttons: Vec::new(),
+        }
+    }
+
+    fn button_mut(&mut self, button: KeyCode) -> &mut ButtonChatter {
+        let index = match self.buttons.iter().position(|(code, _)| *code == button) {
+            Some(index) => index,
+            None => {
+                self.buttons.push((button, ButtonChatter::default()));
+                self.buttons.len() - 1
+            }
+        };
+        &mut self.buttons[index].1
+    }
+
+    /// Filter one event into `out`. Anything but a button event passes straight through;
+    /// a button event may be dropped, held back, or let through along with a press
+    /// that was held back.
+    pub fn filter(&mut self, params: &ChatterParams, event: InputEvent, out: &mut Vec<InputEvent>) {
+        if !params.enabled || event.event_type() != EventType::KEY {
+            out.push(event);
+            return;
+        }
+        let code = KeyCode(event.code());
+        let thresholds = params.thresholds(code);
+        let button = self.button_mut(code);
+        let at = event_time(&event);
+
+        match event.value() {
+            1 => {
+                if button
+                    .last_release
+                    .is_some_and(|release| at.saturating_sub(release) < thresholds.min_gap)
+                {
+                    button.bouncing = true;
+                    button.suppressed += 1;
+                    debug!(
+                        "Dropped bounced press of {code:?}, {} dropped so far",
+                        button.suppressed
+                    );
+                } else if thresholds.min_press > Duration::ZERO {
+                    button.held = Some(event);
+                } else {
+                    out.push(event);
+                }
+            }
+            0 => {
+                button.last_release = Some(at);
+                if button.bouncing {
+                    button.bouncing = false;
+                    button.suppressed += 1;
+                } else if let Some(press) = button.held.take() {
+                    if at.saturating_sub(event_time(&press)) < thresholds.min_press {
+                        button.suppressed += 2;
+                        debug!(
+                            "Dropped phantom click of {code:?}, {} dropped so far",
+                            button.suppressed
+                        );
+                    } else {
+                        out.push(press);
+                        out.push(event);
+                    }
+                } else {
+                    out.push(event);
+                }
+            }
+            // Autorepeat of a press that was dropped or is still held back
+            _ if button.bouncing || button.held.is_some() => {}
+            _ => out.push(event),
+        }
+    }
+
+    /// When the next held press has lasted long enough to be let through
+    pub fn deadline(&self, params: &ChatterParams) -> Option<Duration> {
+        self.buttons
+            .iter()
+            .filter_map(|(code, button)| {
+                let press = button.held.as_ref()?;
+                Some(event_time(press) + params.thresholds(*code).min_press)
+            })
+            .min()
+    }
+
+    /// Let through, into `out`, the held presses that have lasted long enough by `now`
+    pub fn expire(&mut self, params: &ChatterParams, now: Duration, out: &mut Vec<InputEvent>) {
+        for (code, button) in &mut self.buttons {
+            if let Some(press) = &button.held
+                && now >= event_time(press) + params.thresholds(*code).min_press
+            {
+                out.extend(button.held.take());
+            }
+        }
+    }
+
+    /// Presses and releases dropped so far, over all buttons
+    pub fn suppressed(&self) -> u64 {
+        self.buttons
+            .iter()
+            .map(|(_, button)| button.suppressed)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> ChatterParams {
+        ChatterParams { enabled: true, .. }
+    }
+
+    fn button(code: KeyCode, value: i32, ms: u64) -> InputEvent {
+        let time = Duration::from_secs(10) + Duration::from_millis(ms);
+        InputEvent::from(libc::input_event {
+            time: libc::timeval {
+                tv_sec: time.as_secs() as libc::time_t,
+                tv_usec: time.subsec_micros() as libc::suseconds_t,
+            },
+            type_: EventType::KEY.0,
+            code: code.0,
+            value,
+        })
+    }
+
+    /// Run a trace of (button, value, ms) events and return the (button, value) pairs
+    /// that come through
+    fn run(
+        params: &ChatterParams,
+        filter: &mut ChatterFilter,
+        trace: &[(KeyCode, i32, u64)],
+    ) -> Vec<(KeyCode, i32)> {
+        let mut out = Vec::new();
+        for &(code, value, ms) in trace {
+            filter.filter(params, button(code, value, ms), &mut out);
+        }
+        out.iter()
+            .map(|event| (KeyCode(event.code()), event.value()))
+            .collect()
+    }
+
+    #[test]
+    fn test_bounce_after_release_is_dropped() {
+        let mut filter = ChatterFilter::new();
+        let trace = [
+            (KeyCode::BTN_LEFT, 1, 0),
+            (KeyCode::BTN_LEFT, 0, 80),
+            // Phantom second click 10 ms later
+            (KeyCode::BTN_LEFT, 1, 90),
+            (KeyCode::BTN_LEFT, 0, 95),
+            // A real click a while later
+            (KeyCode::BTN_LEFT, 1, 300),
+            (KeyCode::BTN_LEFT, 0, 380),
+        ];
+        let out = run(&params(), &mut filter, &trace);
+
+        assert_eq!(
+            out,
+            [
+                (KeyCode::BTN_LEFT, 1),
+                (KeyCode::BTN_LEFT, 0),
+                (KeyCode::BTN_LEFT, 1),
+                (KeyCode::BTN_LEFT, 0),
+            ]
+        );
+        assert_eq!(filter.suppressed(), 2);
+    }
+
+    #[test]
+    fn test_thresholds_are_per_button() {
+        let params = ChatterParams {
+            buttons: vec![(
+                KeyCode::BTN_RIGHT,
+                ChatterThresholds {
+                    min_gap: Duration::ZERO,
+                    ..
+                },
+            )],
+            ..params()
+        };
+        let mut filter = ChatterFilter::new();
+        let trace = [
+            (KeyCode::BTN_RIGHT, 1, 0),
+            (KeyCode::BTN_RIGHT, 0, 50),
+            (KeyCode::BTN_RIGHT, 1, 60),
+            (KeyCode::BTN_LEFT, 1, 100),
+            (KeyCode::BTN_LEFT, 0, 150),
+            (KeyCode::BTN_LEFT, 1, 160),
+        ];
+        let out = run(&params, &mut filter, &trace);
+
+        assert_eq!(out.len(), 5);
+        assert_eq!(filter.suppressed(), 1);
+    }
+
+    #[test]
+    fn test_short_press_is_dropped_when_enabled() {
+        let params = ChatterParams {
+            default: ChatterThresholds {
+                min_press: Duration::from_millis(10),
+                ..
+            },
+            ..params()
+        };
+        let mut filter = ChatterFilter::new();
+
+        // A 3 ms blip never comes through
+        let out = run(
+            &params,
+            &mut filter,
+            &[(KeyCode::BTN_SIDE, 1, 0), (KeyCode::BTN_SIDE, 0, 3)],
+        );
+        assert!(out.is_empty());
+        assert_eq!(filter.suppressed(), 2);
+
+        // A real press is let through once it has lasted long enough
+        let out = run(&params, &mut filter, &[(KeyCode::BTN_SIDE, 1, 500)]);
+        assert!(out.is_empty());
+        let deadline = filter.deadline(&params).unwrap();
+        assert_eq!(
+            deadline,
+            Duration::from_secs(10) + Duration::from_millis(510)
+        );
+
+        let mut out = Vec::new();
+        filter.expire(&params, deadline, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].value(), 1);
+        assert_eq!(filter.deadline(&params), None);
+    }
+}
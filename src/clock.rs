@@ -0,0 +1,88 @@
+use nix::time::{ClockId, clock_gettime};
+use std::cell::Cell;
+use std::time::Duration;
+
+/// Source of the current time for everything time-based in the scroll pipeline.
+/// Times are offsets from the clock's epoch and must never run backwards.
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// `CLOCK_MONOTONIC`, the clock evdev timestamps are switched to with `EVIOCSCLOCKID`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .map(Duration::from)
+            .unwrap_or_default()
+    }
+}
+
+/// A clock that only moves when told to, for deterministic tests and simulations
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: Cell<Duration>,
+}
+
+impl MockClock {
+    pub fn new(start: Duration) -> Self {
+        Self {
+            now: Cell::new(start),
+        }
+    }
+
+    /// Move the clock forward by `step`
+    pub fn advance(&self, step: Duration) {
+        self.now.set(self.now.get() + step);
+    }
+
+    /// Jump to `time`, which must not be earlier than the current time
+    pub fn set(&self, time: Duration) {
+        debug_assert!(time >= self.now.get(), "MockClock must not run backwards");
+        self.now.set(time);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_told() {
+        let clock = MockClock::new(Duration::from_secs(5));
+        assert_eq!(clock.now(), Duration::from_secs(5));
+        assert_eq!(clock.now(), Duration::from_secs(5));
+
+        clock.advance(Duration::from_micros(250));
+        assert_eq!(
+            clock.now(),
+            Duration::from_secs(5) + Duration::from_micros(250)
+        );
+
+        clock.set(Duration::from_secs(6));
+        assert_eq!(clock.now(), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn test_monotonic_clock_does_not_run_backwards() {
+        let clock = MonotonicClock;
+        let first = clock.now();
+        let second = clock.now();
+        assert!(first > Duration::ZERO);
+        assert!(second >= first);
+    }
+}
@@ -0,0 +1,1106 @@
+use crate::profile::{DeviceInfo, DeviceMatch, NamePattern, Profile};
+use crate::{
+    AnxiousParams, AnxiousParamsError, AxisMapping, ButtonModifier, ChatterParams,
+    ChatterThresholds, Curve, DebounceParams, DirectionalCurve, DragParams, KeyboardParams,
+    KineticParams, ModifierMode, OutputLimits, PointerParams, ResetPolicy, ScrollParams,
+    SmoothingParams, VelocityParams,
+};
+use evdev::KeyCode;
+use regex::Regex;
+use serde::de;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Where the daemon looks for its configuration when `--config` is not given
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/anxious-scroll-daemon/config.toml";
+
+/// The documented example configuration shipped with the daemon, and what
+/// `--print-default-config` prints. It holds the defaults, which a test keeps in sync
+/// with the structs.
+pub const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+
+/// A configuration that could not be loaded
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Not valid TOML, an unknown key, or a value of the wrong type. The message
+    /// points at the line and key.
+    #[error(transparent)]
+    Parse(#[from] toml::de::Error),
+    /// Well formed, but a value is out of range
+    #[error("Invalid `{key}`: {reason}")]
+    Invalid { key: String, reason: String },
+    /// An [`Override`] names an unknown key or has a value of the wrong type
+    #[error("Invalid override `{assignment}`")]
+    Override {
+        assignment: String,
+        #[source]
+        source: toml::de::Error,
+    },
+    /// A profile doesn't make for a valid configuration when laid over the top level
+    #[error("In profile `{name}`")]
+    Profile {
+        name: String,
+        #[source]
+        source: Box<ConfigError>,
+    },
+}
+
+/// A value for a single configuration key, overriding the file. Written `KEY=VALUE`
+/// with a dotted key, e.g. `scroll.kinetic.friction=2.5`. The value is TOML, and taken
+/// as a string if it isn't valid TOML, so `scroll.drag.button=BTN_SIDE` works too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl Override {
+    pub fn new(key: impl Into<String>, value: impl Into<toml::Value>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Set the key in `table`, a serialized configuration, creating the tables on the
+    /// way. Whatever the key held before is replaced as a whole.
+    fn apply(&self, mut table: &mut toml::Table) -> Result<(), ConfigError> {
+        let mut path = self.key.split('.');
+        let last = path.next_back().unwrap_or_default();
+        for name in path {
+            table = match table
+                .entry(name)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            {
+                toml::Value::Table(table) => table,
+                _ => return Err(invalid(&self.key, format!("`{name}` is not a table"))),
+            };
+        }
+        table.insert(last.to_owned(), self.value.clone());
+        Ok(())
+    }
+}
+
+impl FromStr for Override {
+    type Err = ConfigError;
+
+    fn from_str(assignment: &str) -> Result<Self, Self::Err> {
+        let Some((key, value)) = assignment.split_once('=') else {
+            return Err(invalid(assignment, "expected KEY=VALUE"));
+        };
+        let key = key.trim();
+        if key.is_empty() || key.split('.').any(str::is_empty) {
+            return Err(invalid(assignment, "expected a dotted key before `=`"));
+        }
+        let value = value.trim();
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+        Ok(Self::new(key, value))
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// Which devices the daemon reads
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceConfig {
+    /// Path of the mouse, e.g. `/dev/input/event3`. Searched for when not set.
+    pub path: Option<PathBuf> = None,
+    /// Watch keyboards for Shift and Alt
+    pub keyboard_modifiers: bool = false,
+    /// Keyboards to watch, instead of searching for them
+    pub keyboards: Vec<PathBuf> = Vec::new(),
+}
+
+/// Everything the daemon can be configured with
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    pub device: DeviceConfig = DeviceConfig { .. },
+    pub scroll: ScrollParams<DirectionalCurve<Curve>>,
+    /// Settings for particular devices, the first one that matches applies
+    pub profiles: Vec<Profile> = Vec::new(),
+}
+
+impl Config {
+    /// Parse and validate a configuration
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read, parse and validate the configuration file at `path`
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::from_toml(&text)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("configuration is always representable as TOML")
+    }
+
+    /// This configuration with `overrides` applied in order. Unlike the other
+    /// constructors this doesn't validate, so more changes can be made first.
+    pub fn with_overrides(&self, overrides: &[Override]) -> Result<Self, ConfigError> {
+        let mut config = self.clone();
+        for assignment in overrides {
+            let mut table = toml::Table::try_from(&config)
+                .expect("configuration is always representable as TOML");
+            assignment.apply(&mut table)?;
+            config = table.try_into().map_err(|source| ConfigError::Override {
+                assignment: assignment.to_string(),
+                source,
+            })?;
+        }
+        Ok(config)
+    }
+
+    /// The first profile that matches `device`, if any
+    pub fn profile_for(&self, device: &DeviceInfo) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.matches.matches(device))
+    }
+
+    /// The configuration for a device `profile` matches: its settings laid over these,
+    /// validated, and without any profiles of its own
+    pub fn with_profile(&self, profile: &Profile) -> Result<Self, ConfigError> {
+        let in_profile = |source| ConfigError::Profile {
+            name: profile.name.clone(),
+            source: Box::new(source),
+        };
+        let mut scroll = toml::Table::try_from(&self.scroll)
+            .expect("configuration is always representable as TOML");
+        merge(&mut scroll, &profile.scroll);
+        let config = Self {
+            device: self.device.clone(),
+            scroll: scroll
+                .try_into()
+                .map_err(|source| in_profile(ConfigError::Parse(source)))?,
+            profiles: Vec::new(),
+        };
+        config.validate().map_err(in_profile)?;
+        Ok(config)
+    }
+
+    /// Check every value is in range, naming the first one that isn't
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let scroll = &self.scroll;
+        for (axis, curve) in [
+            ("vertical", &scroll.vertical),
+            ("horizontal", &scroll.horizontal),
+        ] {
+            validate_curve(&format!("scroll.{axis}.positive"), &curve.positive)?;
+            validate_curve(&format!("scroll.{axis}.negative"), &curve.negative)?;
+        }
+
+        let kinetic = &scroll.kinetic;
+        at_least(
+            "scroll.kinetic.min_flick_velocity",
+            kinetic.min_flick_velocity,
+            0.0,
+        )?;
+        positive("scroll.kinetic.max_velocity", kinetic.max_velocity)?;
+        positive("scroll.kinetic.friction", kinetic.friction)?;
+        positive("scroll.kinetic.stop_velocity", kinetic.stop_velocity)?;
+        nonzero("scroll.kinetic.interval", kinetic.interval)?;
+
+        let smoothing = &scroll.smoothing;
+        at_least(
+            "scroll.smoothing.threshold",
+            smoothing.threshold as f32,
+            0.0,
+        )?;
+        nonzero("scroll.smoothing.interval", smoothing.interval)?;
+
+        validate_velocity("scroll.velocity", &scroll.velocity)?;
+
+        if let Some(decay) = scroll.reset.decay {
+            nonzero("scroll.reset.decay", decay)?;
+        }
+
+        let limits = &scroll.limits;
+        positive("scroll.limits.max_per_event", limits.max_per_event as f32)?;
+        positive("scroll.limits.max_per_frame", limits.max_per_frame as f32)?;
+        positive("scroll.limits.max_per_second", limits.max_per_second as f32)?;
+
+        for (index, modifier) in scroll.modifiers.iter().enumerate() {
+            validate_mode(&format!("scroll.modifiers[{index}].mode"), &modifier.mode)?;
+        }
+        if let Some(mode) = &scroll.keyboard.alt {
+            validate_mode("scroll.keyboard.alt", mode)?;
+        }
+
+        let drag = &scroll.drag;
+        positive("scroll.drag.units_per_pixel", drag.units_per_pixel)?;
+        at_least("scroll.drag.click_slop", drag.click_slop as f32, 0.0)?;
+        at_least("scroll.drag.autoscroll_speed", drag.autoscroll_speed, 0.0)?;
+        at_least(
+            "scroll.drag.autoscroll_dead_zone",
+            drag.autoscroll_dead_zone,
+            0.0,
+        )?;
+        nonzero("scroll.drag.autoscroll_interval", drag.autoscroll_interval)?;
+
+        validate_curve("scroll.pointer.curve", &scroll.pointer.curve)?;
+
+        if scroll.debounce.min_run == 0 {
+            return Err(invalid("scroll.debounce.min_run", "must be at least 1"));
+        }
+
+        for (index, profile) in self.profiles.iter().enumerate() {
+            if profile.name.is_empty() {
+                return Err(invalid(&format!("profiles[{index}].name"), "must be set"));
+            }
+            if profile.matches.is_empty() {
+                return Err(invalid(
+                    &format!("profiles[{index}].matches"),
+                    "needs at least one rule",
+                ));
+            }
+            self.with_profile(profile)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lay `over` onto `table` key by key. Tables are merged, except ones with a `type`,
+/// such as curves, which replace what they are laid over like any other value.
+fn merge(table: &mut toml::Table, over: &toml::Table) {
+    for (key, value) in over {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(over))
+                if !over.contains_key("type") =>
+            {
+                merge(table, over)
+            }
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn invalid(key: &str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.to_owned(),
+        reason: reason.into(),
+    }
+}
+
+fn at_least(key: &str, value: f32, min: f32) -> Result<(), ConfigError> {
+    // Written so that NaN fails too
+    if value >= min && value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(key, format!("must be at least {min}, got {value}")))
+    }
+}
+
+fn positive(key: &str, value: f32) -> Result<(), ConfigError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(key, format!("must be greater than 0, got {value}")))
+    }
+}
+
+fn nonzero(key: &str, value: Duration) -> Result<(), ConfigError> {
+    if value.is_zero() {
+        Err(invalid(key, "must be longer than 0 ms"))
+    } else {
+        Ok(())
+    }
+}
+
+/// A sensitivity cap at the base sensitivity makes the curve flat, and one below it
+/// makes the curve slow down as the wheel speeds up
+fn above_base(key: &str, value: f32, base_sens: f32) -> Result<(), ConfigError> {
+    if value > base_sens && value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(
+            key,
+            format!("must be greater than base_sens ({base_sens}), got {value}"),
+        ))
+    }
+}
+
+fn validate_curve(key: &str, curve: &Curve) -> Result<(), ConfigError> {
+    let field = |name: &str| format!("{key}.{name}");
+    match curve {
+        Curve::Logistic(curve) => curve.check().map_err(|error| match error {
+            AnxiousParamsError::BaseSens(value) => invalid(
+                &field("base_sens"),
+                format!("must be greater than 0, got {value}"),
+            ),
+            AnxiousParamsError::MaxSens {
+                base_sens,
+                max_sens,
+            } => invalid(
+                &field("max_sens"),
+                format!("must be greater than base_sens ({base_sens}), got {max_sens}"),
+            ),
+            AnxiousParamsError::RampUpRate(value) => invalid(
+                &field("ramp_up_rate"),
+                format!("must be at least 0, got {value}"),
+            ),
+        }),
+        Curve::Linear(curve) => {
+            positive(&field("base_sens"), curve.base_sens)?;
+            at_least(&field("accel"), curve.accel, 0.0)?;
+            above_base(&field("max_sens"), curve.max_sens, curve.base_sens)
+        }
+        Curve::Power(curve) => {
+            positive(&field("base_sens"), curve.base_sens)?;
+            at_least(&field("scale"), curve.scale, 0.0)?;
+            positive(&field("exponent"), curve.exponent)?;
+            above_base(&field("max_sens"), curve.max_sens, curve.base_sens)
+        }
+        Curve::Classic(curve) => {
+            positive(&field("base_sens"), curve.base_sens)?;
+            at_least(&field("offset"), curve.offset, 0.0)?;
+            at_least(&field("accel"), curve.accel, 0.0)?;
+            above_base(&field("cap"), curve.cap, curve.base_sens)
+        }
+        Curve::Natural(curve) => {
+            positive(&field("base_sens"), curve.base_sens)?;
+            above_base(&field("max_sens"), curve.max_sens, curve.base_sens)?;
+            at_least(&field("decay_rate"), curve.decay_rate, 0.0)?;
+            at_least(&field("offset"), curve.offset, 0.0)
+        }
+    }
+}
+
+fn validate_velocity(key: &str, velocity: &VelocityParams) -> Result<(), ConfigError> {
+    match *velocity {
+        VelocityParams::Instant => Ok(()),
+        VelocityParams::Window { window } => nonzero(&format!("{key}.window"), window),
+        VelocityParams::Ema { time_constant } => {
+            nonzero(&format!("{key}.time_constant"), time_constant)
+        }
+        VelocityParams::Kalman {
+            process_noise,
+            measurement_noise,
+        } => {
+            positive(&format!("{key}.process_noise"), process_noise)?;
+            positive(&format!("{key}.measurement_noise"), measurement_noise)
+        }
+    }
+}
+
+fn validate_mode(key: &str, mode: &ModifierMode) -> Result<(), ConfigError> {
+    match *mode {
+        ModifierMode::Precision { sens } => positive(&format!("{key}.sens"), sens),
+        ModifierMode::Boost { multiplier } => positive(&format!("{key}.multiplier"), multiplier),
+    }
+}
+
+/// Implements `Serialize` and `Deserialize` for a settings struct through the derives on
+/// its `#[serde(remote)]` definition. Serde's derives can't parse default field values,
+/// so structs that have them are described again here, field for field. Deserializing
+/// builds the struct without `..`, so a field missing from the definition won't compile.
+macro_rules! remote_settings {
+    ($ty:ident $(<$gen:ident>)? => $def:ident) => {
+        impl$(<$gen: Serialize>)? Serialize for $ty$(<$gen>)? {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                $def::serialize(self, serializer)
+            }
+        }
+
+        impl<'de $(, $gen: Deserialize<'de> + Default)?> Deserialize<'de> for $ty$(<$gen>)? {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                $def::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+/// A [`Duration`] as whole milliseconds
+pub(crate) mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// An optional [`Duration`] as whole milliseconds. `None` leaves the key out, as TOML
+/// has no null.
+mod optional_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&(duration.as_millis() as u64)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        u64::deserialize(deserializer).map(|ms| Some(Duration::from_millis(ms)))
+    }
+}
+
+/// An optional [`ModifierMode`], with `"off"` standing for `None` since leaving the
+/// key out means the default
+mod optional_mode {
+    use crate::ModifierMode;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Off {
+        Off,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged, expecting = "\"off\" or a modifier mode table")]
+    enum ModeSetting {
+        Off(Off),
+        Mode(ModifierMode),
+    }
+
+    pub fn serialize<S: Serializer>(
+        mode: &Option<ModifierMode>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match *mode {
+            Some(mode) => ModeSetting::Mode(mode),
+            None => ModeSetting::Off(Off::Off),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ModifierMode>, D::Error> {
+        Ok(match ModeSetting::deserialize(deserializer)? {
+            ModeSetting::Off(_) => None,
+            ModeSetting::Mode(mode) => Some(mode),
+        })
+    }
+}
+
+/// Per-button settings as a table keyed by button name, e.g.
+/// `[scroll.chatter.buttons.BTN_LEFT]`
+mod button_map {
+    use evdev::KeyCode;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        buttons: &[(KeyCode, T)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(buttons.iter().map(|(button, value)| (button, value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(KeyCode, T)>, D::Error> {
+        BTreeMap::deserialize(deserializer).map(|buttons| buttons.into_iter().collect())
+    }
+}
+
+/// How [`AnxiousParams`] are written, without the constant derived from them. They are
+/// taken unchecked, for [`Config::validate`] to name the key that is out of range.
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnxiousSetting {
+    base_sens: f32,
+    max_sens: f32,
+    ramp_up_rate: f32,
+}
+
+impl Default for AnxiousSetting {
+    fn default() -> Self {
+        Self::from(&AnxiousParams::default())
+    }
+}
+
+impl From<&AnxiousParams> for AnxiousSetting {
+    fn from(params: &AnxiousParams) -> Self {
+        Self {
+            base_sens: params.base_sens(),
+            max_sens: params.max_sens(),
+            ramp_up_rate: params.ramp_up_rate(),
+        }
+    }
+}
+
+impl Serialize for AnxiousParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AnxiousSetting::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnxiousParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let setting = AnxiousSetting::deserialize(deserializer)?;
+        Ok(Self::new_unchecked(
+            setting.base_sens,
+            setting.max_sens,
+            setting.ramp_up_rate,
+        ))
+    }
+}
+
+impl Serialize for NamePattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NamePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map(Self).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Config", default = "Config::default", deny_unknown_fields)]
+struct ConfigSetting {
+    device: DeviceConfig,
+    scroll: ScrollParams<DirectionalCurve<Curve>>,
+    profiles: Vec<Profile>,
+}
+remote_settings!(Config => ConfigSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "DeviceConfig",
+    default = "DeviceConfig::default",
+    deny_unknown_fields
+)]
+struct DeviceConfigSetting {
+    path: Option<PathBuf>,
+    keyboard_modifiers: bool,
+    keyboards: Vec<PathBuf>,
+}
+remote_settings!(DeviceConfig => DeviceConfigSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Profile", default = "Profile::default", deny_unknown_fields)]
+struct ProfileSetting {
+    name: String,
+    matches: DeviceMatch,
+    scroll: toml::Table,
+}
+remote_settings!(Profile => ProfileSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "DeviceMatch",
+    default = "DeviceMatch::default",
+    deny_unknown_fields
+)]
+struct DeviceMatchSetting {
+    bus: Option<u16>,
+    vendor: Option<u16>,
+    product: Option<u16>,
+    name: Option<NamePattern>,
+    phys: Option<String>,
+    by_id: Option<String>,
+}
+remote_settings!(DeviceMatch => DeviceMatchSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "ScrollParams",
+    default = "ScrollParams::default",
+    deny_unknown_fields
+)]
+#[serde(bound(
+    serialize = "C: Serialize",
+    deserialize = "C: Deserialize<'de> + Default"
+))]
+struct ScrollParamsSetting<C> {
+    vertical: C,
+    horizontal: C,
+    legacy_notches: bool,
+    kinetic: KineticParams,
+    smoothing: SmoothingParams,
+    velocity: VelocityParams,
+    reset: ResetPolicy,
+    limits: OutputLimits,
+    modifiers: Vec<ButtonModifier>,
+    keyboard: KeyboardParams,
+    drag: DragParams,
+    pointer: PointerParams,
+    mapping: AxisMapping,
+    debounce: DebounceParams,
+    chatter: ChatterParams,
+}
+remote_settings!(ScrollParams<C> => ScrollParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "KineticParams",
+    default = "KineticParams::default",
+    deny_unknown_fields
+)]
+struct KineticParamsSetting {
+    enabled: bool,
+    min_flick_velocity: f32,
+    max_velocity: f32,
+    friction: f32,
+    stop_velocity: f32,
+    #[serde(with = "millis")]
+    start_delay: Duration,
+    #[serde(with = "millis")]
+    interval: Duration,
+}
+remote_settings!(KineticParams => KineticParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "SmoothingParams",
+    default = "SmoothingParams::default",
+    deny_unknown_fields
+)]
+struct SmoothingParamsSetting {
+    enabled: bool,
+    threshold: i32,
+    #[serde(with = "millis")]
+    duration: Duration,
+    #[serde(with = "millis")]
+    interval: Duration,
+}
+remote_settings!(SmoothingParams => SmoothingParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "ResetPolicy",
+    default = "ResetPolicy::default",
+    deny_unknown_fields
+)]
+struct ResetPolicySetting {
+    on_reversal: bool,
+    #[serde(with = "optional_millis")]
+    idle_timeout: Option<Duration>,
+    #[serde(with = "optional_millis")]
+    decay: Option<Duration>,
+}
+remote_settings!(ResetPolicy => ResetPolicySetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "OutputLimits",
+    default = "OutputLimits::default",
+    deny_unknown_fields
+)]
+struct OutputLimitsSetting {
+    max_per_event: i32,
+    max_per_frame: i32,
+    max_per_second: i32,
+}
+remote_settings!(OutputLimits => OutputLimitsSetting);
+
+/// `swallow` is the only optional key of a [`ButtonModifier`]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ButtonModifier", deny_unknown_fields)]
+struct ButtonModifierSetting {
+    button: KeyCode,
+    mode: ModifierMode,
+    #[serde(default)]
+    swallow: bool,
+}
+remote_settings!(ButtonModifier => ButtonModifierSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "KeyboardParams",
+    default = "KeyboardParams::default",
+    deny_unknown_fields
+)]
+struct KeyboardParamsSetting {
+    shift_horizontal: bool,
+    #[serde(with = "optional_mode")]
+    alt: Option<ModifierMode>,
+}
+remote_settings!(KeyboardParams => KeyboardParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "DragParams",
+    default = "DragParams::default",
+    deny_unknown_fields
+)]
+struct DragParamsSetting {
+    enabled: bool,
+    button: KeyCode,
+    units_per_pixel: f32,
+    click_slop: i32,
+    autoscroll: bool,
+    autoscroll_speed: f32,
+    autoscroll_dead_zone: f32,
+    #[serde(with = "millis")]
+    autoscroll_interval: Duration,
+}
+remote_settings!(DragParams => DragParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "PointerParams",
+    default = "PointerParams::default",
+    deny_unknown_fields
+)]
+struct PointerParamsSetting {
+    enabled: bool,
+    curve: Curve,
+}
+remote_settings!(PointerParams => PointerParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "AxisMapping",
+    default = "AxisMapping::default",
+    deny_unknown_fields
+)]
+struct AxisMappingSetting {
+    swap: bool,
+    invert_vertical: bool,
+    invert_horizontal: bool,
+}
+remote_settings!(AxisMapping => AxisMappingSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "DebounceParams",
+    default = "DebounceParams::default",
+    deny_unknown_fields
+)]
+struct DebounceParamsSetting {
+    enabled: bool,
+    #[serde(with = "millis")]
+    window: Duration,
+    min_run: u32,
+}
+remote_settings!(DebounceParams => DebounceParamsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "ChatterThresholds",
+    default = "ChatterThresholds::default",
+    deny_unknown_fields
+)]
+struct ChatterThresholdsSetting {
+    #[serde(with = "millis")]
+    min_gap: Duration,
+    #[serde(with = "millis")]
+    min_press: Duration,
+}
+remote_settings!(ChatterThresholds => ChatterThresholdsSetting);
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    remote = "ChatterParams",
+    default = "ChatterParams::default",
+    deny_unknown_fields
+)]
+struct ChatterParamsSetting {
+    enabled: bool,
+    default: ChatterThresholds,
+    #[serde(with = "button_map")]
+    buttons: Vec<(KeyCode, ChatterThresholds)>,
+}
+remote_settings!(ChatterParams => ChatterParamsSetting);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ButtonModifier, ChatterThresholds, LinearCurve};
+    use evdev::KeyCode;
+
+    /// The set of keys in a table and all the tables nested in it, as dotted paths
+    fn keys(table: &toml::Table, prefix: &str, out: &mut Vec<String>) {
+        for (key, value) in table {
+            let path = format!("{prefix}{key}");
+            if let toml::Value::Table(table) = value {
+                keys(table, &format!("{path}."), out);
+            }
+            out.push(path);
+        }
+    }
+
+    #[test]
+    fn test_example_config_matches_defaults() {
+        // Same values...
+        assert_eq!(
+            Config::from_toml(EXAMPLE_CONFIG).unwrap(),
+            Config::default()
+        );
+
+        // ...and every setting is in the example, so none goes undocumented
+        let mut example = Vec::new();
+        keys(&toml::from_str(EXAMPLE_CONFIG).unwrap(), "", &mut example);
+        let mut defaults = Vec::new();
+        keys(
+            &toml::from_str(&Config::default().to_toml()).unwrap(),
+            "",
+            &mut defaults,
+        );
+        example.sort();
+        defaults.sort();
+        assert_eq!(example, defaults);
+    }
+
+    #[test]
+    fn test_partial_config_keeps_other_defaults() {
+        let config = Config::from_toml(
+            r#"
+            [device]
+            path = "/dev/input/event7"
+
+            [scroll.vertical.negative]
+            type = "linear"
+            accel = 1.5
+
+            [scroll.debounce]
+            enabled = true
+            window = 25
+
+            [scroll.keyboard]
+            alt = "off"
+
+            [[scroll.modifiers]]
+            button = "BTN_SIDE"
+            mode = { type = "boost", multiplier = 3.0 }
+
+            [scroll.chatter.buttons.BTN_LEFT]
+            min_gap = 50
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.device.path, Some(PathBuf::from("/dev/input/event7")));
+        assert_eq!(
+            config.scroll.vertical.negative,
+            Curve::Linear(LinearCurve {
+                accel: 1.5,
+                ..LinearCurve::default()
+            })
+        );
+        assert_eq!(config.scroll.vertical.positive, Curve::default());
+        assert_eq!(config.scroll.horizontal, DirectionalCurve::default());
+        assert_eq!(config.scroll.debounce.window, Duration::from_millis(25));
+        assert_eq!(config.scroll.debounce.min_run, 3);
+        assert_eq!(config.scroll.keyboard.alt, None);
+        assert!(config.scroll.keyboard.shift_horizontal);
+        assert_eq!(
+            config.scroll.modifiers,
+            [ButtonModifier {
+                button: KeyCode::BTN_SIDE,
+                mode: ModifierMode::Boost { multiplier: 3.0 },
+                swallow: false,
+            }]
+        );
+        assert_eq!(
+            config.scroll.chatter.buttons,
+            [(
+                KeyCode::BTN_LEFT,
+                ChatterThresholds {
+                    min_gap: Duration::from_millis(50),
+                    ..
+                }
+            )]
+        );
+
+        // Everything survives a round trip
+        assert_eq!(Config::from_toml(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let error = |text: &str| Config::from_toml(text).unwrap_err().to_string();
+
+        let typo = error("[scroll.kinetic]\nfriciton = 2.0");
+        assert!(typo.contains("friciton"), "{typo}");
+
+        let wrong_type = error("[scroll.smoothing]\nduration = \"fast\"");
+        assert!(wrong_type.contains("duration"), "{wrong_type}");
+
+        let out_of_range = error(
+            "[scroll.horizontal.positive]\ntype = \"logistic\"\nbase_sens = 2.0\nmax_sens = 1.0",
+        );
+        assert_eq!(
+            out_of_range,
+            "Invalid `scroll.horizontal.positive.max_sens`: must be greater than base_sens (2), got 1"
+        );
+
+        // A cap equal to the base sensitivity would make a flat curve
+        let flat =
+            error("[scroll.vertical.negative]\ntype = \"classic\"\nbase_sens = 3.0\ncap = 3.0");
+        assert_eq!(
+            flat,
+            "Invalid `scroll.vertical.negative.cap`: must be greater than base_sens (3), got 3"
+        );
+
+        let mode = error("[scroll.keyboard]\nalt = { type = \"precision\", sens = 0.0 }");
+        assert!(
+            mode.starts_with("Invalid `scroll.keyboard.alt.sens`"),
+            "{mode}"
+        );
+    }
+
+    #[test]
+    fn test_overrides_set_any_key() {
+        let overrides: Vec<Override> = [
+            "scroll.kinetic.enabled=true",
+            "scroll.drag.button=BTN_SIDE",
+            "scroll.vertical.negative={ type = \"linear\", accel = 0.5 }",
+            "scroll.reset.idle_timeout=300",
+        ]
+        .iter()
+        .map(|text| text.parse().unwrap())
+        .collect();
+        let config = Config::default().with_overrides(&overrides).unwrap();
+
+        assert!(config.scroll.kinetic.enabled);
+        assert_eq!(config.scroll.drag.button, KeyCode::BTN_SIDE);
+        assert_eq!(
+            config.scroll.vertical.negative,
+            Curve::Linear(LinearCurve {
+                accel: 0.5,
+                ..LinearCurve::default()
+            })
+        );
+        assert_eq!(
+            config.scroll.reset.idle_timeout,
+            Some(Duration::from_millis(300))
+        );
+        // Keys that weren't overridden keep their values
+        assert_eq!(
+            config.scroll.vertical.positive,
+            Config::default().scroll.vertical.positive
+        );
+
+        // Bad keys and malformed assignments are errors
+        let unknown = Override::new("scroll.kinetic.friciton", 2.0);
+        assert!(Config::default().with_overrides(&[unknown]).is_err());
+        assert!("scroll.kinetic.enabled".parse::<Override>().is_err());
+        assert!("=true".parse::<Override>().is_err());
+    }
+
+    #[test]
+    fn test_profiles_layer_over_the_top_level() {
+        let config = Config::from_toml(
+            r#"
+            [[profiles]]
+            name = "G502"
+            [profiles.matches]
+            vendor = 0x046d
+            name = "G502"
+            [profiles.scroll]
+            mapping.invert_vertical = true
+            vertical.positive.max_sens = 20.0
+            vertical.negative = { type = "linear", accel = 1.0 }
+
+            [scroll.vertical.positive]
+            type = "logistic"
+            ramp_up_rate = 0.5
+            "#,
+        )
+        .unwrap();
+
+        let mouse = DeviceInfo {
+            vendor: 0x046d,
+            name: "Logitech G502 HERO Gaming Mouse".to_owned(),
+            ..
+        };
+        let profile = config.profile_for(&mouse).unwrap();
+        assert_eq!(profile.name, "G502");
+        assert_eq!(config.profile_for(&DeviceInfo { .. }), None);
+
+        let merged = config.with_profile(profile).unwrap();
+        assert!(merged.scroll.mapping.invert_vertical);
+        // Keys within a table are laid over the top level, tagged tables replace it
+        assert_eq!(
+            merged.scroll.vertical.positive,
+            Curve::Logistic(AnxiousParams::new_unchecked(1.0, 20.0, 0.5))
+        );
+        assert_eq!(
+            merged.scroll.vertical.negative,
+            Curve::Linear(LinearCurve {
+                accel: 1.0,
+                ..LinearCurve::default()
+            })
+        );
+        assert_eq!(merged.scroll.horizontal, config.scroll.horizontal);
+        assert!(merged.profiles.is_empty());
+
+        // Profiles are checked when loading, whether or not a device matches them
+        let error = Config::from_toml(
+            "[[profiles]]\nname = \"slow\"\nmatches.bus = 5\nscroll.vertical.positive.base_sens = 0.0",
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "In profile `slow`");
+        let source = std::error::Error::source(&error).unwrap().to_string();
+        assert!(
+            source.starts_with("Invalid `scroll.vertical.positive.base_sens`"),
+            "{source}"
+        );
+
+        let error = Config::from_toml("[[profiles]]\nname = \"any\"").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid `profiles[0].matches`: needs at least one rule"
+        );
+    }
+
+    #[test]
+    fn test_velocity_estimator_is_selectable() {
+        let config =
+            Config::from_toml("[scroll.velocity]\ntype = \"ema\"\ntime_constant = 50").unwrap();
+        let ema = VelocityParams::Ema {
+            time_constant: Duration::from_millis(50),
+        };
+        assert_eq!(config.scroll.velocity, ema);
+
+        // The state runs the estimator the params select, also after a reload
+        let mut state = crate::ScrollState::with_params(&config.scroll);
+        assert_eq!(*state.vertical.velocity.params(), ema);
+        state.reconfigure(&Config::default().scroll);
+        assert_eq!(*state.horizontal.velocity.params(), VelocityParams::Instant);
+
+        let error = Config::from_toml("[scroll.velocity]\ntype = \"window\"\nwindow = 0")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Invalid `scroll.velocity.window`: must be longer than 0 ms"
+        );
+    }
+}
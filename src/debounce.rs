@@ -0,0 +1,217 @@
+use log::debug;
+use std::time::Duration;
+
+/// Parameters for dropping the spurious reverse ticks of worn wheel encoders
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DebounceParams {
+    pub enabled: bool = false,
+    /// A reverse tick this soon after a tick of an ongoing scroll is held back until
+    /// the next tick shows whether it was real
+    pub window: Duration = Duration::from_millis(40),
+    /// Ticks in one direction it takes for a scroll to count as ongoing
+    pub min_run: u32 = 3,
+}
+
+/// What to do with a wheel tick after debouncing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Debounced {
+    /// Scroll as usual
+    Pass,
+    /// Held back as a possibly spurious reversal, nothing to scroll yet
+    Hold,
+    /// The held reversal was real: scroll it at the time it happened, then this tick
+    Release { value: i32, at: Duration },
+}
+
+/// Debounce state of one wheel axis
+#[derive(Debug, Clone, PartialEq)]
+pub struct Debouncer {
+    /// Sign of the ongoing scroll, 0 before the first tick
+    pub direction: i32,
+    /// Consecutive ticks in `direction`
+    pub run: u32,
+    /// Monotonic time of the last tick in `direction`
+    pub last: Duration,
+    /// The reverse tick being held back, and when it happened
+    pub held: Option<(i32, Duration)>,
+    /// Reverse ticks dropped as spurious
+    pub filtered: u64,
+    /// Reverse ticks held back that turned out to be real
+    pub released: u64,
+}
+
+impl Debouncer {
+    pub const fn new() -> Self {
+        Self {
+            direction: 0,
+            run: 0,
+            last: Duration::ZERO,
+            held: None,
+            filtered: 0,
+            released: 0,
+        }
+    }
+
+    /// Follow a tick of `value` at `at` and say what to do with it
+    pub fn filter(&mut self, params: &DebounceParams, value: i32, at: Duration) -> Debounced {
+        let direction = value.signum();
+        if !params.enabled || direction == 0 {
+            return Debounced::Pass;
+        }
+
+        if let Some((held, held_at)) = self.held.take() {
+            if direction == held.signum() {
+                // Two ticks the other way: the scroll really reversed
+                self.released += 1;
+                self.direction = direction;
+                self.run = 2;
+                self.last = at;
+                return Debounced::Release {
+                    value: held,
+                    at: held_at,
+                };
+            }
+            // Back to the ongoing direction: the reverse tick was noise
+            self.filtered += 1;
+            debug!(
+                "Dropped spurious wheel reversal of {held}, {} dropped and {} let through so far",
+                self.filtered, self.released
+            );
+        } else if direction == -self.direction
+            && self.run >= params.min_run
+            && at.saturating_sub(self.last) <= params.window
+        {
+            self.held = Some((value, at));
+            return Debounced::Hold;
+        }
+
+        if direction == self.direction {
+            self.run += 1;
+        } else {
+            self.direction = direction;
+            self.run = 1;
+        }
+        self.last = at;
+        Debounced::Pass
+    }
+
+    /// When the held reversal is let through if no tick settles it first
+    pub fn deadline(&self, params: &DebounceParams) -> Option<Duration> {
+        self.held.map(|(_, at)| at + params.window)
+    }
+
+    /// Let the held reversal through once its window has passed by `now`, as a lone
+    /// tick can't be told apart from a real one-tick correction
+    pub fn expire(&mut self, params: &DebounceParams, now: Duration) -> Option<(i32, Duration)> {
+        let (value, at) = self.held?;
+        if now < at + params.window {
+            return None;
+        }
+        self.held = None;
+        self.released += 1;
+        self.direction = value.signum();
+        self.run = 1;
+        self.last = at;
+        Some((value, at))
+    }
+}
+
+impl Default for Debouncer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> DebounceParams {
+        DebounceParams { enabled: true, .. }
+    }
+
+    /// Run a trace of (ms, value) ticks and return the values that get scrolled
+    fn run(params: &DebounceParams, trace: &[(u64, i32)]) -> (Vec<i32>, Debouncer) {
+        let mut debouncer = Debouncer::new();
+        let mut scrolled = Vec::new();
+        for &(ms, value) in trace {
+            let at = Duration::from_millis(ms);
+            if let Some((held, _)) = debouncer.expire(params, at) {
+                scrolled.push(held);
+            }
+            match debouncer.filter(params, value, at) {
+                Debounced::Pass => scrolled.push(value),
+                Debounced::Hold => {}
+                Debounced::Release { value: held, .. } => {
+                    scrolled.push(held);
+                    scrolled.push(value);
+                }
+            }
+        }
+        (scrolled, debouncer)
+    }
+
+    #[test]
+    fn test_isolated_reversal_is_dropped() {
+        // A fast scroll down with one bogus tick up in the middle
+        let trace = [
+            (0, -120),
+            (8, -120),
+            (16, -120),
+            (24, 120),
+            (32, -120),
+            (40, -120),
+        ];
+        let (scrolled, debouncer) = run(&params(), &trace);
+        assert_eq!(scrolled, [-120, -120, -120, -120, -120]);
+        assert_eq!(debouncer.filtered, 1);
+        assert_eq!(debouncer.released, 0);
+
+        // Nothing is dropped when disabled
+        let (scrolled, _) = run(&DebounceParams::default(), &trace);
+        assert_eq!(scrolled.len(), trace.len());
+    }
+
+    #[test]
+    fn test_real_reversal_is_delayed_not_lost() {
+        let trace = [
+            (0, -120),
+            (8, -120),
+            (16, -120),
+            (24, 120),
+            (32, 120),
+            (40, 120),
+        ];
+        let (scrolled, debouncer) = run(&params(), &trace);
+        assert_eq!(scrolled, [-120, -120, -120, 120, 120, 120]);
+        assert_eq!(debouncer.filtered, 0);
+        assert_eq!(debouncer.released, 1);
+    }
+
+    #[test]
+    fn test_lone_correction_comes_through_after_window() {
+        let params = params();
+        let trace = [(0, -120), (8, -120), (16, -120), (24, 120)];
+        let (scrolled, mut debouncer) = run(&params, &trace);
+        assert_eq!(scrolled, [-120, -120, -120]);
+
+        let deadline = debouncer.deadline(&params).unwrap();
+        assert_eq!(deadline, Duration::from_millis(64));
+        assert_eq!(
+            debouncer.expire(&params, deadline),
+            Some((120, Duration::from_millis(24)))
+        );
+        assert_eq!(debouncer.deadline(&params), None);
+    }
+
+    #[test]
+    fn test_slow_or_short_scrolls_reverse_freely() {
+        // Too few ticks to be an ongoing scroll
+        let (scrolled, _) = run(&params(), &[(0, -120), (8, -120), (16, 120)]);
+        assert_eq!(scrolled, [-120, -120, 120]);
+
+        // Reversal after a pause
+        let (scrolled, _) = run(&params(), &[(0, -120), (8, -120), (16, -120), (100, 120)]);
+        assert_eq!(scrolled, [-120, -120, -120, 120]);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::Axis;
+use evdev::{EventType, InputEvent, KeyCode, RelativeAxisCode};
+use std::time::Duration;
+
+/// Parameters for scrolling by moving the mouse with a button held
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DragParams {
+    pub enabled: bool = false,
+    /// Button that turns pointer motion into scrolling while held
+    pub button: KeyCode = KeyCode::BTN_MIDDLE,
+    /// Hi-res wheel units per pixel of motion, before acceleration
+    pub units_per_pixel: f32 = 4.0,
+    /// Motion in pixels a press may have and still count as a click
+    pub click_slop: i32 = 4,
+    /// A click starts Windows-style autoscroll instead of being passed through as a
+    /// click. Another click, or any other button, stops it.
+    pub autoscroll: bool = false,
+    /// Autoscroll speed, in hi-res units per millisecond per pixel away from the anchor
+    pub autoscroll_speed: f32 = 0.05,
+    /// Distance from the anchor, in pixels, within which autoscroll stands still
+    pub autoscroll_dead_zone: f32 = 8.0,
+    /// Time between two autoscroll events
+    pub autoscroll_interval: Duration = Duration::from_millis(8),
+}
+
+/// What to do with an event, as far as drag scrolling is concerned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DragAction {
+    /// Not ours, handle it as usual
+    PassThrough,
+    /// Keep it from the virtual device
+    Swallow,
+    /// The button was clicked without dragging, emit the click it stood for
+    Click,
+    /// Scroll `axis` by this many hi-res units, before acceleration
+    Scroll(Axis, f32),
+}
+
+/// Continuous scrolling at a speed set by how far the pointer is from where it started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autoscroll {
+    /// Pointer offset from the anchor in pixels, (x, y)
+    pub offset: (f32, f32),
+    /// Monotonic time of the last autoscroll event
+    pub updated: Duration,
+    /// Sub-unit distance not yet emitted, (horizontal, vertical)
+    remainder: (f32, f32),
+}
+
+impl Autoscroll {
+    /// When the next autoscroll event is due
+    pub fn deadline(&self, params: &DragParams) -> Duration {
+        self.updated + params.autoscroll_interval
+    }
+
+    /// Advance to `now` and return the whole hi-res units to scroll since the last
+    /// event, as (horizontal, vertical)
+    pub fn scroll(&mut self, params: &DragParams, now: Duration) -> (i32, i32) {
+        let elapsed_ms = now.saturating_sub(self.updated).as_secs_f32() * 1000.0;
+        self.updated = self.updated.max(now);
+
+        let speed = |offset: f32| {
+            offset.signum()
+                * (offset.abs() - params.autoscroll_dead_zone).max(0.0)
+                * params.autoscroll_speed
+        };
+        // Pointer below the anchor scrolls down, which is a negative wheel value
+        let horizontal = speed(self.offset.0) * elapsed_ms + self.remainder.0;
+        let vertical = -speed(self.offset.1) * elapsed_ms + self.remainder.1;
+        self.remainder = (horizontal.fract(), vertical.fract());
+        (horizontal.trunc() as i32, vertical.trunc() as i32)
+    }
+}
+
+/// Where a drag or autoscroll gesture is at
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DragState {
+    /// The drag button is held
+    pub pressed: bool = false,
+    /// Pixels moved since the drag button was pressed
+    pub moved: i32 = 0,
+    pub autoscroll: Option<Autoscroll> = None,
+    /// The press that stopped autoscroll, whose release must be swallowed too
+    pub stopping: bool = false,
+}
+
+impl DragState {
+    /// Follow an event stamped `at` and say what should become of it
+    pub fn handle(&mut self, params: &DragParams, event: &InputEvent, at: Duration) -> DragAction {
+        if !params.enabled {
+            return DragAction::PassThrough;
+        }
+        match event.event_type() {
+            EventType::KEY if event.code() == params.button.0 => {
+                self.handle_button(params, event, at)
+            }
+            // Any other button stops autoscroll, and still does what it does
+            EventType::KEY => {
+                if event.value() == 1 {
+                    self.autoscroll = None;
+                }
+                DragAction::PassThrough
+            }
+            EventType::RELATIVE => self.handle_motion(params, event),
+            _ => DragAction::PassThrough,
+        }
+    }
+
+    fn handle_button(
+        &mut self,
+        params: &DragParams,
+        event: &InputEvent,
+        at: Duration,
+    ) -> DragAction {
+        match event.value() {
+            1 if self.autoscroll.is_some() => {
+                self.autoscroll = None;
+                self.stopping = true;
+            }
+            1 => {
+                self.pressed = true;
+                self.moved = 0;
+            }
+            0 if self.stopping => self.stopping = false,
+            0 if self.pressed => {
+                self.pressed = false;
+                if self.moved <= params.click_slop {
+                    if !params.autoscroll {
+                        return DragAction::Click;
+                    }
+                    self.autoscroll = Some(Autoscroll {
+                        offset: (0.0, 0.0),
+                        updated: at,
+                        remainder: (0.0, 0.0),
+                    });
+                }
+            }
+            // A release we never saw the press of
+            0 => return DragAction::PassThrough,
+            _ => {}
+        }
+        DragAction::Swallow
+    }
+
+    fn handle_motion(&mut self, params: &DragParams, event: &InputEvent) -> DragAction {
+        let (axis, sign) = match RelativeAxisCode(event.code()) {
+            RelativeAxisCode::REL_X => (Axis::Horizontal, 1.0),
+            // Moving down scrolls down, which is a negative wheel value
+            RelativeAxisCode::REL_Y => (Axis::Vertical, -1.0),
+            _ => return DragAction::PassThrough,
+        };
+        let pixels = event.value();
+
+        if let Some(autoscroll) = &mut self.autoscroll {
+            match axis {
+                Axis::Horizontal => autoscroll.offset.0 += pixels as f32,
+                Axis::Vertical => autoscroll.offset.1 += pixels as f32,
+            }
+            return DragAction::Swallow;
+        }
+        if !self.pressed {
+            return DragAction::PassThrough;
+        }
+        // Small wobbles during a click neither move the pointer nor scroll
+        self.moved = self.moved.saturating_add(pixels.saturating_abs());
+        if self.moved <= params.click_slop {
+            return DragAction::Swallow;
+        }
+        DragAction::Scroll(axis, sign * pixels as f32 * params.units_per_pixel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> DragParams {
+        DragParams { enabled: true, .. }
+    }
+
+    fn button(value: i32) -> InputEvent {
+        InputEvent::new(EventType::KEY.0, KeyCode::BTN_MIDDLE.0, value)
+    }
+
+    fn motion(code: RelativeAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::RELATIVE.0, code.0, value)
+    }
+
+    #[test]
+    fn test_drag_turns_motion_into_scroll() {
+        let params = params();
+        let mut drag = DragState::default();
+        let at = Duration::ZERO;
+
+        assert_eq!(drag.handle(&params, &button(1), at), DragAction::Swallow);
+        // Within the click slop nothing happens yet
+        let down = motion(RelativeAxisCode::REL_Y, 3);
+        assert_eq!(drag.handle(&params, &down, at), DragAction::Swallow);
+        assert_eq!(
+            drag.handle(&params, &down, at),
+            DragAction::Scroll(Axis::Vertical, -12.0)
+        );
+        let right = motion(RelativeAxisCode::REL_X, 2);
+        assert_eq!(
+            drag.handle(&params, &right, at),
+            DragAction::Scroll(Axis::Horizontal, 8.0)
+        );
+        assert_eq!(drag.handle(&params, &button(0), at), DragAction::Swallow);
+
+        // Released, motion moves the pointer again
+        assert_eq!(drag.handle(&params, &down, at), DragAction::PassThrough);
+    }
+
+    #[test]
+    fn test_click_without_drag_is_kept() {
+        let params = params();
+        let mut drag = DragState::default();
+        let at = Duration::ZERO;
+
+        drag.handle(&params, &button(1), at);
+        drag.handle(&params, &motion(RelativeAxisCode::REL_X, 1), at);
+        assert_eq!(drag.handle(&params, &button(0), at), DragAction::Click);
+
+        // Nothing happens when disabled
+        let disabled = DragParams::default();
+        assert_eq!(
+            drag.handle(&disabled, &button(1), at),
+            DragAction::PassThrough
+        );
+    }
+
+    #[test]
+    fn test_autoscroll_speed_follows_distance() {
+        let params = DragParams {
+            autoscroll: true,
+            ..params()
+        };
+        let mut drag = DragState::default();
+        let start = Duration::from_secs(10);
+
+        drag.handle(&params, &button(1), start);
+        drag.handle(&params, &button(0), start);
+        let autoscroll = drag.autoscroll.as_mut().unwrap();
+        // Still within the dead zone
+        assert_eq!(
+            autoscroll.scroll(&params, start + Duration::from_millis(8)),
+            (0, 0)
+        );
+
+        let down = motion(RelativeAxisCode::REL_Y, 28);
+        assert_eq!(drag.handle(&params, &down, start), DragAction::Swallow);
+        let autoscroll = drag.autoscroll.as_mut().unwrap();
+        // 20 pixels past the dead zone at 0.05 units/ms per pixel, for 16 ms
+        assert_eq!(
+            autoscroll.scroll(&params, start + Duration::from_millis(24)),
+            (0, -16)
+        );
+
+        // Clicking again stops, and the click goes nowhere
+        assert_eq!(drag.handle(&params, &button(1), start), DragAction::Swallow);
+        assert_eq!(drag.handle(&params, &button(0), start), DragAction::Swallow);
+        assert!(drag.autoscroll.is_none());
+        assert_eq!(drag.handle(&params, &down, start), DragAction::PassThrough);
+    }
+}
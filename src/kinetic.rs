@@ -0,0 +1,175 @@
+use crate::Axis;
+use std::time::Duration;
+
+/// Parameters for kinetic (inertial) scrolling after a fast flick
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KineticParams {
+    pub enabled: bool = false,
+    /// Output velocity, in hi-res units per millisecond, a flick must reach to coast
+    pub min_flick_velocity: f32 = 50.0,
+    /// Coasting never starts faster than this, in hi-res units per millisecond
+    pub max_velocity: f32 = 200.0,
+    /// Exponential decay rate of the coasting velocity, per second
+    pub friction: f32 = 4.0,
+    /// Coasting stops once the velocity falls below this, in hi-res units per millisecond
+    pub stop_velocity: f32 = 0.1,
+    /// Quiet time after the last wheel event before coasting starts, so the wheel
+    /// itself is never overlapped
+    pub start_delay: Duration = Duration::from_millis(30),
+    /// Time between two coasting events
+    pub interval: Duration = Duration::from_millis(8),
+}
+
+/// A flick that is coasting, or about to once the wheel stays quiet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Momentum {
+    pub axis: Axis,
+    /// Signed velocity in hi-res units per millisecond, as of `updated`
+    pub velocity: f32,
+    /// Monotonic time of the last wheel event or coasting frame
+    pub updated: Duration,
+    /// Whether coasting frames are being emitted yet
+    pub coasting: bool,
+    /// Sub-unit distance not yet emitted
+    remainder: f32,
+}
+
+impl Momentum {
+    /// Arm momentum after an accelerated wheel event, or stop it if the event was
+    /// too slow or went against the current coast
+    pub fn on_scroll(
+        momentum: &mut Option<Momentum>,
+        params: &KineticParams,
+        axis: Axis,
+        velocity: f32,
+        at: Duration,
+    ) {
+        let reversed = momentum
+            .as_ref()
+            .is_some_and(|current| current.axis == axis && current.velocity * velocity < 0.0);
+        if !params.enabled || reversed || velocity.abs() < params.min_flick_velocity {
+            *momentum = None;
+            return;
+        }
+        *momentum = Some(Momentum {
+            axis,
+            velocity: velocity.clamp(-params.max_velocity, params.max_velocity),
+            updated: at,
+            coasting: false,
+            remainder: 0.0,
+        });
+    }
+
+    /// When the next coasting frame is due
+    pub fn deadline(&self, params: &KineticParams) -> Duration {
+        if self.coasting {
+            self.updated + params.interval
+        } else {
+            self.updated + params.start_delay
+        }
+    }
+
+    /// Advance the coast to `now` and return the whole hi-res units travelled since the
+    /// last frame, or `None` once the momentum has run out
+    pub fn coast(&mut self, params: &KineticParams, now: Duration) -> Option<i32> {
+        let Some(elapsed) = now.checked_sub(self.updated) else {
+            return Some(0);
+        };
+        let elapsed_ms = elapsed.as_secs_f32() * 1000.0;
+        let rate = params.friction / 1000.0;
+        let decay = (-rate * elapsed_ms).exp();
+
+        // Distance covered while the velocity decayed: the integral of v * e^(-rate * t)
+        let distance = if rate > 0.0 {
+            self.velocity * (1.0 - decay) / rate
+        } else {
+            self.velocity * elapsed_ms
+        };
+        self.velocity *= decay;
+        self.updated = now;
+
+        if self.velocity.abs() < params.stop_velocity {
+            return None;
+        }
+        // The quiet time before coasting only decays the velocity, the wheel covered it
+        if !self.coasting {
+            self.coasting = true;
+            return Some(0);
+        }
+
+        let distance = distance + self.remainder;
+        let whole = distance.trunc();
+        self.remainder = distance - whole;
+        Some(whole as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> KineticParams {
+        KineticParams { enabled: true, .. }
+    }
+
+    #[test]
+    fn test_slow_scroll_does_not_arm() {
+        let mut momentum = None;
+        Momentum::on_scroll(
+            &mut momentum,
+            &params(),
+            Axis::Vertical,
+            10.0,
+            Duration::ZERO,
+        );
+        assert!(momentum.is_none());
+
+        // Disabled by default
+        let disabled = KineticParams::default();
+        Momentum::on_scroll(
+            &mut momentum,
+            &disabled,
+            Axis::Vertical,
+            100.0,
+            Duration::ZERO,
+        );
+        assert!(momentum.is_none());
+    }
+
+    #[test]
+    fn test_coast_decays_and_stops() {
+        let params = params();
+        let mut momentum = None;
+        Momentum::on_scroll(
+            &mut momentum,
+            &params,
+            Axis::Vertical,
+            -1000.0,
+            Duration::ZERO,
+        );
+        let mut momentum = momentum.unwrap();
+        assert_eq!(momentum.velocity, -params.max_velocity);
+
+        let mut now = momentum.deadline(&params);
+        assert_eq!(now, params.start_delay);
+        assert_eq!(momentum.coast(&params, now), Some(0));
+
+        let mut frames = Vec::new();
+        loop {
+            now = momentum.deadline(&params);
+            match momentum.coast(&params, now) {
+                Some(distance) => frames.push(distance),
+                None => break,
+            }
+            assert!(frames.len() < 1000, "momentum never ran out");
+        }
+
+        assert!(frames.iter().all(|&distance| distance <= 0));
+        assert!(frames.first().unwrap() < frames.last().unwrap());
+        // Total distance approaches v / rate, minus what decayed before coasting
+        let total: i32 = frames.iter().sum();
+        let expected = -params.max_velocity / (params.friction / 1000.0)
+            * (-params.friction * params.start_delay.as_secs_f32()).exp();
+        assert!((total as f32 - expected).abs() < expected.abs() * 0.01);
+    }
+}
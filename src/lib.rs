@@ -1,316 +1,2760 @@
 #![feature(default_field_values)]
 
-use evdev::{EventType, InputEvent, RelativeAxisCode};
-use std::time::SystemTime;
+use evdev::{
+    AttributeSet, AttributeSetRef, EventType, InputEvent, KeyCode, RelativeAxisCode,
+    SynchronizationCode,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, UNIX_EPOCH};
+use thiserror::Error;
 
-/// Parameters for the anxious scroll algorithm
-#[derive(Debug, Clone)]
+pub mod chatter;
+pub mod clock;
+pub mod config;
+pub mod debounce;
+pub mod drag;
+pub mod kinetic;
+pub mod limits;
+pub mod modifiers;
+pub mod pointer;
+pub mod profile;
+pub mod smoothing;
+pub mod velocity;
+
+pub use chatter::{ButtonChatter, ChatterFilter, ChatterParams, ChatterThresholds};
+pub use clock::{Clock, MockClock, MonotonicClock};
+pub use config::{Config, ConfigError, DeviceConfig, Override};
+pub use debounce::{DebounceParams, Debounced, Debouncer};
+pub use drag::{Autoscroll, DragAction, DragParams, DragState};
+pub use kinetic::{KineticParams, Momentum};
+pub use limits::{OutputLimiter, OutputLimits};
+pub use modifiers::{ButtonModifier, KeyboardParams, KeyboardState, ModifiedCurve, ModifierMode};
+pub use pointer::{PointerParams, PointerState};
+pub use profile::{DeviceInfo, DeviceMatch, NamePattern, Profile};
+pub use smoothing::{Smoother, SmoothingParams};
+pub use velocity::{ResetPolicy, VelocityEstimator, VelocityParams};
+
+/// Parameters for the anxious scroll algorithm. Build them with [`AnxiousParams::builder`],
+/// which checks them, or [`AnxiousParams::new_unchecked`].
+#[derive(Debug, Clone, PartialEq)]
 pub struct AnxiousParams {
     /// Base sensitivity to start at
-    pub base_sens: f32,
+    base_sens: f32,
     /// Max sensitivity to taper off towards
-    pub max_sens: f32,
+    max_sens: f32,
     /// How fast to ramp up the logistic function
-    pub ramp_up_rate: f32,
+    ramp_up_rate: f32,
+    /// C of the logistic function, derived from the sensitivities
+    c: f32,
+}
+
+/// Why [`AnxiousParams`] were rejected
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AnxiousParamsError {
+    #[error("base_sens must be greater than 0, got {0}")]
+    BaseSens(f32),
+    #[error("max_sens must be greater than base_sens ({base_sens}), got {max_sens}")]
+    MaxSens { base_sens: f32, max_sens: f32 },
+    #[error("ramp_up_rate must be at least 0, got {0}")]
+    RampUpRate(f32),
+}
+
+impl AnxiousParams {
+    /// Start from the defaults, and check the result on `build`
+    pub fn builder() -> AnxiousParamsBuilder {
+        AnxiousParamsBuilder {
+            params: Self::default(),
+        }
+    }
+
+    /// Parameters taken as they are. Nonsense such as a zero `base_sens` makes for
+    /// infinite or NaN sensitivities rather than an error.
+    pub const fn new_unchecked(base_sens: f32, max_sens: f32, ramp_up_rate: f32) -> Self {
+        Self {
+            base_sens,
+            max_sens,
+            ramp_up_rate,
+            c: (max_sens / base_sens) - 1.0,
+        }
+    }
+
+    pub fn base_sens(&self) -> f32 {
+        self.base_sens
+    }
+
+    pub fn max_sens(&self) -> f32 {
+        self.max_sens
+    }
+
+    pub fn ramp_up_rate(&self) -> f32 {
+        self.ramp_up_rate
+    }
+
+    /// Check the parameters make for a curve rising from `base_sens` to `max_sens`
+    pub fn check(&self) -> Result<(), AnxiousParamsError> {
+        if !(self.base_sens > 0.0 && self.base_sens.is_finite()) {
+            return Err(AnxiousParamsError::BaseSens(self.base_sens));
+        }
+        // Equal sensitivities would make a flat curve, with a `c` of 0
+        if !(self.max_sens > self.base_sens && self.max_sens.is_finite()) {
+            return Err(AnxiousParamsError::MaxSens {
+                base_sens: self.base_sens,
+                max_sens: self.max_sens,
+            });
+        }
+        if !(self.ramp_up_rate >= 0.0 && self.ramp_up_rate.is_finite()) {
+            return Err(AnxiousParamsError::RampUpRate(self.ramp_up_rate));
+        }
+        Ok(())
+    }
 }
 
 impl Default for AnxiousParams {
+    fn default() -> Self {
+        Self::new_unchecked(1.0, 15.0, 0.3)
+    }
+}
+
+/// Builds checked [`AnxiousParams`], see [`AnxiousParams::builder`]
+#[derive(Debug, Clone)]
+pub struct AnxiousParamsBuilder {
+    params: AnxiousParams,
+}
+
+impl AnxiousParamsBuilder {
+    pub fn base_sens(mut self, base_sens: f32) -> Self {
+        self.params.base_sens = base_sens;
+        self
+    }
+
+    pub fn max_sens(mut self, max_sens: f32) -> Self {
+        self.params.max_sens = max_sens;
+        self
+    }
+
+    pub fn ramp_up_rate(mut self, ramp_up_rate: f32) -> Self {
+        self.params.ramp_up_rate = ramp_up_rate;
+        self
+    }
+
+    pub fn build(self) -> Result<AnxiousParams, AnxiousParamsError> {
+        let AnxiousParams {
+            base_sens,
+            max_sens,
+            ramp_up_rate,
+            ..
+        } = self.params;
+        let params = AnxiousParams::new_unchecked(base_sens, max_sens, ramp_up_rate);
+        params.check()?;
+        Ok(params)
+    }
+}
+
+/// A scroll acceleration curve, mapping scroll velocity to a sensitivity multiplier
+pub trait ScrollCurve {
+    /// Sensitivity multiplier for a scroll velocity in hi-res units per millisecond
+    fn sensitivity(&self, vel: f32) -> f32;
+
+    /// Sensitivity multiplier for a wheel event of `value` at velocity `vel`. Only the
+    /// sign of `value` matters, and most curves ignore it.
+    #[inline(always)]
+    fn sensitivity_for(&self, value: f32, vel: f32) -> f32 {
+        let _ = value;
+        self.sensitivity(vel)
+    }
+}
+
+/// We use a logistic function as the default transformation function.
+/// f(vel) = max_sens / (1 + C * e^(-ramp_up_rate * vel)), where
+/// C = (max_sens / (base_sens) - 1
+/// Visualisation: https://www.desmos.com/calculator/grsgyudrch
+impl ScrollCurve for AnxiousParams {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        let exp_term = fast_exp(-self.ramp_up_rate * vel);
+        // Apply the logistic function: max_sens / (1 + c * e^(-ramp_up_rate * vel))
+        self.max_sens / (1.0 + self.c * exp_term)
+    }
+}
+
+/// Linear acceleration with a hard cap.
+/// f(vel) = min(base_sens + accel * vel, max_sens)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinearCurve {
+    /// Sensitivity at zero velocity
+    pub base_sens: f32,
+    /// Sensitivity gained per unit of velocity
+    pub accel: f32,
+    /// Sensitivity cap
+    pub max_sens: f32,
+}
+
+impl Default for LinearCurve {
+    fn default() -> Self {
+        Self {
+            base_sens: 1.0,
+            accel: 0.75,
+            max_sens: 15.0,
+        }
+    }
+}
+
+impl ScrollCurve for LinearCurve {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        (self.base_sens + self.accel * vel).min(self.max_sens)
+    }
+}
+
+/// Power-law acceleration with a hard cap.
+/// f(vel) = min(base_sens + (scale * vel)^exponent, max_sens)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerCurve {
+    /// Sensitivity at zero velocity
+    pub base_sens: f32,
+    /// Velocity scale applied before raising to `exponent`
+    pub scale: f32,
+    /// Exponent of the curve, > 1 ramps up slowly then sharply
+    pub exponent: f32,
+    /// Sensitivity cap
+    pub max_sens: f32,
+}
+
+impl Default for PowerCurve {
+    fn default() -> Self {
+        Self {
+            base_sens: 1.0,
+            scale: 0.5,
+            exponent: 2.0,
+            max_sens: 15.0,
+        }
+    }
+}
+
+impl ScrollCurve for PowerCurve {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        (self.base_sens + (self.scale * vel).powf(self.exponent)).min(self.max_sens)
+    }
+}
+
+/// Quake-style "classic" acceleration: nothing happens below `offset`,
+/// then sensitivity grows linearly until it hits `cap`.
+/// f(vel) = min(base_sens + accel * max(vel - offset, 0), cap)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassicCurve {
+    /// Sensitivity below the offset
+    pub base_sens: f32,
+    /// Velocity below which no acceleration is applied
+    pub offset: f32,
+    /// Sensitivity gained per unit of velocity above the offset
+    pub accel: f32,
+    /// Sensitivity cap
+    pub cap: f32,
+}
+
+impl Default for ClassicCurve {
+    fn default() -> Self {
+        Self {
+            base_sens: 1.0,
+            offset: 2.0,
+            accel: 1.0,
+            cap: 15.0,
+        }
+    }
+}
+
+impl ScrollCurve for ClassicCurve {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        (self.base_sens + self.accel * (vel - self.offset).max(0.0)).min(self.cap)
+    }
+}
+
+/// "Natural" acceleration in the spirit of libinput's adaptive profile: flat up to
+/// `offset`, then easing towards `max_sens` with no hard corner.
+/// f(vel) = max_sens - (max_sens - base_sens) * e^(-decay_rate * max(vel - offset, 0))
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NaturalCurve {
+    /// Sensitivity below the offset
+    pub base_sens: f32,
+    /// Sensitivity the curve converges to
+    pub max_sens: f32,
+    /// How fast the curve approaches `max_sens`
+    pub decay_rate: f32,
+    /// Velocity below which no acceleration is applied
+    pub offset: f32,
+}
+
+impl Default for NaturalCurve {
     fn default() -> Self {
         Self {
             base_sens: 1.0,
             max_sens: 15.0,
-            ramp_up_rate: 0.3,
+            decay_rate: 0.1,
+            offset: 1.0,
+        }
+    }
+}
+
+impl ScrollCurve for NaturalCurve {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        let excess = (vel - self.offset).max(0.0);
+        self.max_sens - (self.max_sens - self.base_sens) * fast_exp(-self.decay_rate * excess)
+    }
+}
+
+/// Any of the built-in curves, for picking one at runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Curve {
+    Logistic(AnxiousParams),
+    Linear(LinearCurve),
+    Power(PowerCurve),
+    Classic(ClassicCurve),
+    Natural(NaturalCurve),
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::Logistic(AnxiousParams::default())
+    }
+}
+
+impl Curve {
+    /// Set the sensitivity the curve tops out at, whatever its kind calls it. It is not
+    /// checked against the other parameters.
+    pub fn set_max_sens(&mut self, max_sens: f32) {
+        match self {
+            Self::Logistic(curve) => {
+                *curve = AnxiousParams::new_unchecked(curve.base_sens, max_sens, curve.ramp_up_rate)
+            }
+            Self::Linear(curve) => curve.max_sens = max_sens,
+            Self::Power(curve) => curve.max_sens = max_sens,
+            Self::Classic(curve) => curve.cap = max_sens,
+            Self::Natural(curve) => curve.max_sens = max_sens,
+        }
+    }
+}
+
+impl ScrollCurve for Curve {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        match self {
+            Self::Logistic(curve) => curve.sensitivity(vel),
+            Self::Linear(curve) => curve.sensitivity(vel),
+            Self::Power(curve) => curve.sensitivity(vel),
+            Self::Classic(curve) => curve.sensitivity(vel),
+            Self::Natural(curve) => curve.sensitivity(vel),
+        }
+    }
+}
+
+/// Separate curves for the two directions of a wheel, e.g. to scroll down through long
+/// logs faster than back up. Positive values scroll up (or right on the horizontal wheel).
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[serde(bound(deserialize = "C: Deserialize<'de> + Default"))]
+pub struct DirectionalCurve<C = AnxiousParams> {
+    /// Curve for scrolling up or right
+    pub positive: C,
+    /// Curve for scrolling down or left
+    pub negative: C,
+}
+
+impl<C: Clone> DirectionalCurve<C> {
+    /// Use the same curve in both directions
+    pub fn symmetric(curve: C) -> Self {
+        Self {
+            positive: curve.clone(),
+            negative: curve,
+        }
+    }
+}
+
+impl<C: ScrollCurve> ScrollCurve for DirectionalCurve<C> {
+    /// Without a direction to go by, the positive curve is used
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        self.positive.sensitivity(vel)
+    }
+
+    #[inline(always)]
+    fn sensitivity_for(&self, value: f32, vel: f32) -> f32 {
+        if value < 0.0 {
+            self.negative.sensitivity(vel)
+        } else {
+            self.positive.sensitivity(vel)
+        }
+    }
+}
+
+impl<C: ScrollCurve + ?Sized> ScrollCurve for &C {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        (**self).sensitivity(vel)
+    }
+
+    #[inline(always)]
+    fn sensitivity_for(&self, value: f32, vel: f32) -> f32 {
+        (**self).sensitivity_for(value, vel)
+    }
+}
+
+impl<C: ScrollCurve + ?Sized> ScrollCurve for Box<C> {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        (**self).sensitivity(vel)
+    }
+
+    #[inline(always)]
+    fn sensitivity_for(&self, value: f32, vel: f32) -> f32 {
+        (**self).sensitivity_for(value, vel)
+    }
+}
+
+/// Intervals shorter than this are clamped to it: one polling period of an 8 kHz device,
+/// the fastest real hardware reports at. Events sharing a timestamp therefore read as
+/// the highest velocity a device can produce instead of an infinite one.
+pub const MIN_INTERVAL: Duration = Duration::from_micros(125);
+
+/// Interval assumed when an event is timestamped before the previous one (out-of-order
+/// delivery, clock changes). It reads as a gentle scroll.
+pub const OUT_OF_ORDER_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Time from `prev_time` to `timestamp`, clamped to [`MIN_INTERVAL`], moving `prev_time`
+/// on to `timestamp`. An event older than the previous one reads as a gentle
+/// [`OUT_OF_ORDER_INTERVAL`] and leaves `prev_time` where it is, so time never runs
+/// backwards for later events.
+pub fn elapsed_since(prev_time: &mut Duration, timestamp: Duration) -> Duration {
+    match timestamp.checked_sub(*prev_time) {
+        Some(duration) => {
+            *prev_time = timestamp;
+            duration.max(MIN_INTERVAL)
+        }
+        None => OUT_OF_ORDER_INTERVAL,
+    }
+}
+
+/// Scale `value` by `sens`, carrying the fractional part over to the next call in
+/// `remainder` so slow motion still adds up. A leftover from the opposite direction is
+/// dropped rather than eating into a reversal.
+pub fn carry_scaled(value: f32, sens: f32, remainder: &mut f32) -> i32 {
+    if *remainder * value < 0.0 {
+        *remainder = 0.0;
+    }
+    let scaled = value * sens + *remainder;
+    let output = scaled.trunc();
+    *remainder = scaled - output;
+    output as i32
+}
+
+/// Timestamp of an event as an offset from its clock's epoch. Once the device has been
+/// switched to `CLOCK_MONOTONIC` with `EVIOCSCLOCKID` this is time since boot.
+pub fn event_time(event: &InputEvent) -> Duration {
+    event
+        .timestamp()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// State for tracking scroll velocity over time on a single axis
+#[derive(Debug)]
+pub struct AnxiousState {
+    /// Monotonic timestamp of the previous wheel event, i.e. when the wheel was last active
+    pub prev_time: Duration,
+    /// Sign of the previous wheel event, 0.0 before the first one
+    pub direction: f32 = 0.0,
+    /// The next wheel event starts a new gesture and is not measured against the
+    /// previous one
+    pub restart: bool = false,
+    /// Sub-unit part of the previous output that has not been emitted yet
+    pub remainder: f32 = 0.0,
+    /// Turns the stream of wheel events into a velocity for the curve
+    pub velocity: VelocityEstimator = VelocityEstimator::new(VelocityParams::Instant),
+    /// Accelerated hi-res output not yet turned into a legacy notch
+    pub notch_remainder: i32 = 0,
+    /// The source device has no hi-res code for this axis, so its legacy notches are
+    /// upconverted to hi-res units and accelerated instead of dropped
+    pub emulate_hi_res: bool = false,
+    /// Signed velocity of the accelerated output at the previous wheel event, in hi-res
+    /// units per millisecond
+    pub last_velocity: f32 = 0.0,
+    /// Accelerated output still being spread over the coming frames
+    pub smoother: Smoother = Smoother::new(),
+    /// How much of the output limits this axis has used up
+    pub limiter: OutputLimiter = OutputLimiter::new(),
+    /// Spurious reverse ticks held back or dropped on this axis
+    pub debounce: Debouncer = Debouncer::new(),
+}
+
+impl AnxiousState {
+    pub fn new() -> Self {
+        Self::with_clock(&MonotonicClock)
+    }
+
+    /// Start tracking from the current time of `clock`
+    pub fn with_clock<C: Clock + ?Sized>(clock: &C) -> Self {
+        Self {
+            prev_time: clock.now(),
+            ..
+        }
+    }
+
+    /// Start tracking with the velocity estimator selected by `params`
+    pub fn with_velocity(params: VelocityParams) -> Self {
+        Self {
+            velocity: VelocityEstimator::new(params),
+            ..Self::new()
+        }
+    }
+
+    /// Forget the velocity built up so far, as if the wheel had been idle for a long time
+    pub fn reset_velocity(&mut self) {
+        self.velocity.reset();
+        self.restart = true;
+    }
+
+    /// Apply `policy` to a wheel event of `value` at `timestamp`, before it is
+    /// accelerated
+    pub fn apply_reset_policy(&mut self, policy: &ResetPolicy, value: f32, timestamp: Duration) {
+        let idle = timestamp.saturating_sub(self.prev_time);
+        let reversed = self.direction * value < 0.0;
+        if (policy.on_reversal && reversed)
+            || policy.idle_timeout.is_some_and(|timeout| idle >= timeout)
+        {
+            self.reset_velocity();
+        } else if let Some(decay) = policy.decay {
+            self.velocity
+                .decay((-idle.as_secs_f32() / decay.as_secs_f32()).exp());
+        }
+    }
+
+    /// Add accelerated hi-res output to the notch accumulator and take the whole legacy
+    /// notches it completes, one per [`HI_RES_PER_NOTCH`] units like the kernel does.
+    /// Like the kernel, a change of direction starts the count over.
+    pub fn take_notches(&mut self, hi_res: i32) -> i32 {
+        if self.notch_remainder.signum() * hi_res.signum() < 0 {
+            self.notch_remainder = 0;
+        }
+        self.notch_remainder += hi_res;
+        let notches = self.notch_remainder / HI_RES_PER_NOTCH;
+        self.notch_remainder -= notches * HI_RES_PER_NOTCH;
+        notches
+    }
+}
+
+impl Default for AnxiousState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A scroll wheel axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Vertical,
+    Horizontal,
+}
+
+impl Axis {
+    /// The axis a relative event code scrolls, and whether it is the hi-res code
+    pub fn from_code(code: u16) -> Option<(Self, bool)> {
+        match RelativeAxisCode(code) {
+            RelativeAxisCode::REL_WHEEL_HI_RES => Some((Self::Vertical, true)),
+            RelativeAxisCode::REL_WHEEL => Some((Self::Vertical, false)),
+            RelativeAxisCode::REL_HWHEEL_HI_RES => Some((Self::Horizontal, true)),
+            RelativeAxisCode::REL_HWHEEL => Some((Self::Horizontal, false)),
+            _ => None,
+        }
+    }
+
+    pub fn hi_res_code(self) -> RelativeAxisCode {
+        match self {
+            Self::Vertical => RelativeAxisCode::REL_WHEEL_HI_RES,
+            Self::Horizontal => RelativeAxisCode::REL_HWHEEL_HI_RES,
+        }
+    }
+
+    pub fn legacy_code(self) -> RelativeAxisCode {
+        match self {
+            Self::Vertical => RelativeAxisCode::REL_WHEEL,
+            Self::Horizontal => RelativeAxisCode::REL_HWHEEL,
+        }
+    }
+}
+
+/// Whether `axes` (a device's relative axes) are those of a mouse with a scroll wheel.
+/// A horizontal wheel isn't required, so basic mice whose plain wheel gets upconverted
+/// are found too.
+pub fn is_wheel_mouse(axes: &AttributeSetRef<RelativeAxisCode>) -> bool {
+    axes.contains(RelativeAxisCode::REL_X)
+        && axes.contains(RelativeAxisCode::REL_Y)
+        && axes.contains(RelativeAxisCode::REL_WHEEL)
+}
+
+/// Relative axes for the virtual device: those of the source device, plus the hi-res
+/// code of every wheel it has, since legacy-only wheels are upconverted. A wheel the
+/// device lacks is left out.
+pub fn virtual_relative_axes(
+    axes: &AttributeSetRef<RelativeAxisCode>,
+) -> AttributeSet<RelativeAxisCode> {
+    let mut virtual_axes: AttributeSet<RelativeAxisCode> = axes.iter().collect();
+    for axis in [Axis::Vertical, Axis::Horizontal] {
+        if axes.contains(axis.legacy_code()) {
+            virtual_axes.insert(axis.hi_res_code());
+        }
+    }
+    virtual_axes
+}
+
+/// Remapping of the wheel axes, applied to wheel input before it is accelerated
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AxisMapping {
+    /// Exchange the vertical and horizontal wheels
+    pub swap: bool = false,
+    /// Reverse the vertical wheel ("natural" scrolling), after any swap
+    pub invert_vertical: bool = false,
+    /// Reverse the horizontal wheel, after any swap
+    pub invert_horizontal: bool = false,
+}
+
+impl AxisMapping {
+    /// Where a wheel movement of `value` on `axis` ends up
+    pub fn apply(&self, axis: Axis, value: i32) -> (Axis, i32) {
+        let axis = match (self.swap, axis) {
+            (false, axis) => axis,
+            (true, Axis::Vertical) => Axis::Horizontal,
+            (true, Axis::Horizontal) => Axis::Vertical,
+        };
+        let invert = match axis {
+            Axis::Vertical => self.invert_vertical,
+            Axis::Horizontal => self.invert_horizontal,
+        };
+        (
+            axis,
+            if invert {
+                value.saturating_neg()
+            } else {
+                value
+            },
+        )
+    }
+}
+
+/// Hi-res wheel units per legacy wheel notch, as defined by the kernel
+pub const HI_RES_PER_NOTCH: i32 = 120;
+
+/// Curves for the vertical and horizontal wheel, tuned independently
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScrollParams<C = AnxiousParams> {
+    pub vertical: C,
+    pub horizontal: C,
+    /// Regenerate legacy `REL_WHEEL`/`REL_HWHEEL` notches from the accelerated hi-res
+    /// output, for clients that don't understand hi-res scrolling
+    pub legacy_notches: bool = true,
+    /// Keep scrolling with decaying speed after a fast flick
+    pub kinetic: KineticParams = KineticParams { .. },
+    /// Spread large accelerated outputs over several frames instead of jumping
+    pub smoothing: SmoothingParams = SmoothingParams { .. },
+    /// How the velocity of each wheel is estimated from its events
+    pub velocity: VelocityParams = VelocityParams::Instant,
+    /// When the velocity built up on an axis is forgotten
+    pub reset: ResetPolicy = ResetPolicy { .. },
+    /// Safety bounds on the accelerated output of each axis
+    pub limits: OutputLimits = OutputLimits { .. },
+    /// Buttons that change how the wheel scrolls while held
+    pub modifiers: Vec<ButtonModifier> = Vec::new(),
+    /// What held keyboard modifiers do to the wheel
+    pub keyboard: KeyboardParams = KeyboardParams { .. },
+    /// Scroll by moving the mouse with a button held
+    pub drag: DragParams = DragParams { .. },
+    /// Acceleration of pointer motion, independent of the wheels
+    pub pointer: PointerParams = PointerParams { .. },
+    /// Inversion and swapping of the wheel axes
+    pub mapping: AxisMapping = AxisMapping { .. },
+    /// Dropping of spurious reverse ticks from worn wheels
+    pub debounce: DebounceParams = DebounceParams { .. },
+    /// Dropping of phantom clicks from worn button switches
+    pub chatter: ChatterParams = ChatterParams { .. },
+}
+
+impl<C: Clone> ScrollParams<C> {
+    /// Use the same curve on both axes
+    pub fn both(curve: C) -> Self {
+        Self {
+            vertical: curve.clone(),
+            horizontal: curve,
+            ..
+        }
+    }
+}
+
+impl<C> ScrollParams<C> {
+    pub fn axis(&self, axis: Axis) -> &C {
+        match axis {
+            Axis::Vertical => &self.vertical,
+            Axis::Horizontal => &self.horizontal,
+        }
+    }
+}
+
+/// Velocity tracking for both wheel axes and the pointer. Each keeps its own history, so a
+/// flick on one wheel does not accelerate the other.
+#[derive(Debug, Default)]
+pub struct ScrollState {
+    pub vertical: AnxiousState,
+    pub horizontal: AnxiousState,
+    /// The flick being coasted by kinetic scrolling, if any
+    pub momentum: Option<Momentum> = None,
+    /// Modifier buttons currently held, in the order they were pressed
+    pub held_modifiers: Vec<KeyCode> = Vec::new(),
+    /// Modifier keys held on the monitored keyboards
+    pub keyboard: KeyboardState = KeyboardState { .. },
+    /// Drag scrolling or autoscroll in progress
+    pub drag: DragState = DragState { .. },
+    /// Button presses and releases held back or dropped as chatter
+    pub chatter: ChatterFilter = ChatterFilter::new(),
+    /// Velocity tracking for pointer motion
+    pub pointer: PointerState,
+}
+
+impl ScrollState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking both axes from the current time of `clock`
+    pub fn with_clock<C: Clock + ?Sized>(clock: &C) -> Self {
+        Self {
+            vertical: AnxiousState::with_clock(clock),
+            horizontal: AnxiousState::with_clock(clock),
+            pointer: PointerState::with_clock(clock),
+            ..
+        }
+    }
+
+    /// Start tracking both axes with the velocity estimator selected by `params`
+    pub fn with_params<C>(params: &ScrollParams<C>) -> Self {
+        let mut state = Self::new();
+        state.reconfigure(params);
+        state
+    }
+
+    /// Bring the state in line with `params` after they were swapped at runtime: switch
+    /// to another velocity estimator if they select one, and drop what features they
+    /// turn off had going. Autoscroll and kinetic scrolling would otherwise keep
+    /// scrolling, with nothing left to stop them.
+    pub fn reconfigure<C>(&mut self, params: &ScrollParams<C>) {
+        for axis_state in [&mut self.vertical, &mut self.horizontal] {
+            if *axis_state.velocity.params() != params.velocity {
+                axis_state.velocity = VelocityEstimator::new(params.velocity);
+            }
+        }
+        if !params.drag.enabled {
+            self.drag = DragState { .. };
+        } else if !params.drag.autoscroll {
+            self.drag.autoscroll = None;
+        }
+        if !params.kinetic.enabled {
+            self.momentum = None;
+        }
+    }
+
+    /// Emulate hi-res scrolling on every wheel axis that `axes` (the source device's
+    /// relative axes) only reports in legacy notches
+    pub fn emulate_missing_hi_res(&mut self, axes: &AttributeSetRef<RelativeAxisCode>) {
+        for axis in [Axis::Vertical, Axis::Horizontal] {
+            self.axis_mut(axis).emulate_hi_res =
+                axes.contains(axis.legacy_code()) && !axes.contains(axis.hi_res_code());
+        }
+    }
+
+    /// The mode of the most recently pressed modifier button still held, or else of a
+    /// held keyboard modifier
+    pub fn active_modifier<C>(&self, params: &ScrollParams<C>) -> Option<ModifierMode> {
+        self.held_modifiers
+            .iter()
+            .rev()
+            .find_map(|button| {
+                params
+                    .modifiers
+                    .iter()
+                    .find(|modifier| modifier.button == *button)
+            })
+            .map(|modifier| modifier.mode)
+            .or(params.keyboard.alt.filter(|_| self.keyboard.alt()))
+    }
+
+    /// Follow the presses and releases of modifier buttons. Returns whether the event
+    /// should be kept from the virtual device.
+    fn track_modifier<C>(&mut self, params: &ScrollParams<C>, event: &InputEvent) -> bool {
+        let button = KeyCode(event.code());
+        let Some(modifier) = params
+            .modifiers
+            .iter()
+            .find(|modifier| modifier.button == button)
+        else {
+            return false;
+        };
+        match event.value() {
+            0 => self.held_modifiers.retain(|held| *held != button),
+            1 if !self.held_modifiers.contains(&button) => self.held_modifiers.push(button),
+            _ => {}
+        }
+        modifier.swallow
+    }
+
+    pub fn axis_mut(&mut self, axis: Axis) -> &mut AnxiousState {
+        match axis {
+            Axis::Vertical => &mut self.vertical,
+            Axis::Horizontal => &mut self.horizontal,
+        }
+    }
+}
+
+// Exponential lookup table (LUT) configuration and data
+//
+// The following macro invocation expands at compile time to define:
+// - EXP_LOOKUP_START: f32    (inclusive lower bound)
+// - EXP_LOOKUP_END: f32      (inclusive upper bound)
+// - EXP_LOOKUP_STEPS: usize  (number of samples; must be >= 2)
+// - EXP_LOOKUP_STEP_SIZE: f32
+// - EXP_LOOKUP_LUT: [f32; EXP_LOOKUP_STEPS]  (samples of e^x over [START, END])
+//
+// Inputs must be literals because the proc macro computes the LUT at
+// compile time and emits the array as a literal. Adjust START/END/STEPS
+// here to regenerate the table.
+exp_lut_macro::exp_lut_macro!(
+    start: -12.0,
+    end: 12.0,
+    steps: 10000
+);
+
+#[inline(always)]
+fn fast_exp(x: f32) -> f32 {
+    if (EXP_LOOKUP_START..=EXP_LOOKUP_END).contains(&x) {
+        let index = ((x - EXP_LOOKUP_START) / EXP_LOOKUP_STEP_SIZE) as usize;
+        EXP_LOOKUP_LUT[index]
+    } else {
+        x.exp()
+    }
+}
+
+#[inline(always)]
+/// Scale a scroll delta by the sensitivity `curve` gives for the current scroll velocity
+/// and the direction of the delta.
+/// The velocity is estimated by the state's [`VelocityEstimator`] from the time since
+/// the previous wheel event.
+pub fn apply_anxious_scroll<C: ScrollCurve + ?Sized>(
+    value: f32,
+    timestamp: Duration,
+    curve: &C,
+    anxious_state: &mut AnxiousState,
+) -> i32 {
+    let elapsed_time = elapsed_since(&mut anxious_state.prev_time, timestamp);
+    // First event of a new gesture, there is no previous one to measure against
+    let elapsed_time = if std::mem::take(&mut anxious_state.restart) {
+        OUT_OF_ORDER_INTERVAL
+    } else {
+        elapsed_time
+    };
+    if value != 0.0 {
+        anxious_state.direction = value.signum();
+    }
+
+    let vel = anxious_state
+        .velocity
+        .update(value.abs(), elapsed_time.as_secs_f32() * 1000.0);
+    let sens = curve.sensitivity_for(value, vel);
+    anxious_state.last_velocity = value.signum() * vel * sens;
+    carry_scaled(value, sens, &mut anxious_state.remainder)
+}
+
+/// Push an accelerated hi-res scroll on `axis` produced at `at`, clamped to the output
+/// limits, followed by the legacy notches it completes
+fn push_scroll<C>(
+    event_batch: &mut Vec<InputEvent>,
+    axis: Axis,
+    value: i32,
+    axis_state: &mut AnxiousState,
+    params: &ScrollParams<C>,
+    at: Duration,
+) {
+    let limited = axis_state.limiter.limit(&params.limits, axis, value, at);
+    if limited == 0 && value != 0 {
+        return;
+    }
+    let value = limited;
+    // new_now() is not necessary here as the kernel will update the time field
+    // when it emits the events to any programs reading the event "file".
+    event_batch.push(InputEvent::new(
+        EventType::RELATIVE.0,
+        axis.hi_res_code().0,
+        value,
+    ));
+    if params.legacy_notches {
+        let notches = axis_state.take_notches(value);
+        if notches != 0 {
+            event_batch.push(InputEvent::new(
+                EventType::RELATIVE.0,
+                axis.legacy_code().0,
+                notches,
+            ));
+        }
+    }
+}
+
+/// Push accelerated pointer motion, leaving out axes that did not move
+fn push_motion(event_batch: &mut Vec<InputEvent>, x: i32, y: i32) {
+    for (code, value) in [(RelativeAxisCode::REL_X, x), (RelativeAxisCode::REL_Y, y)] {
+        if value != 0 {
+            event_batch.push(InputEvent::new(EventType::RELATIVE.0, code.0, value));
+        }
+    }
+}
+
+/// Take a wheel movement of `value` hi-res units on the device's `axis` at `at` through
+/// axis remapping, then scroll it
+fn wheel_input<C: ScrollCurve>(
+    event_batch: &mut Vec<InputEvent>,
+    axis: Axis,
+    value: i32,
+    at: Duration,
+    params: &ScrollParams<C>,
+    state: &mut ScrollState,
+) {
+    let (axis, value) = params.mapping.apply(axis, value);
+    let axis =
+        if axis == Axis::Vertical && params.keyboard.shift_horizontal && state.keyboard.shift() {
+            Axis::Horizontal
+        } else {
+            axis
+        };
+    scroll_wheel(event_batch, axis, value as f32, at, params, state);
+}
+
+/// Accelerate a wheel movement of `value` hi-res units on `axis` at `at` and push the
+/// result, or queue it when it is being smoothed
+fn scroll_wheel<C: ScrollCurve>(
+    event_batch: &mut Vec<InputEvent>,
+    axis: Axis,
+    value: f32,
+    at: Duration,
+    params: &ScrollParams<C>,
+    state: &mut ScrollState,
+) {
+    let modifier = state.active_modifier(params);
+    let axis_state = state.axis_mut(axis);
+    axis_state.apply_reset_policy(&params.reset, value, at);
+    let modified_value = match modifier {
+        Some(mode) => apply_anxious_scroll(
+            value,
+            at,
+            &ModifiedCurve {
+                curve: params.axis(axis),
+                mode,
+            },
+            axis_state,
+        ),
+        None => apply_anxious_scroll(value, at, params.axis(axis), axis_state),
+    };
+    let immediate = axis_state
+        .smoother
+        .submit(&params.smoothing, modified_value, at);
+    if immediate != 0 || axis_state.smoother.is_idle() {
+        push_scroll(event_batch, axis, immediate, axis_state, params, at);
+    }
+    let velocity = state.axis_mut(axis).last_velocity;
+    Momentum::on_scroll(&mut state.momentum, &params.kinetic, axis, velocity, at);
+}
+
+/// Handle one input event that made it past the button chatter filter
+fn input_event<C: ScrollCurve>(
+    event_batch: &mut Vec<InputEvent>,
+    event: InputEvent,
+    params: &ScrollParams<C>,
+    state: &mut ScrollState,
+) {
+    if event.event_type() == EventType::RELATIVE
+        && let Some((axis, hi_res)) = Axis::from_code(event.code())
+    {
+        let value = if hi_res {
+            event.value()
+        } else if state.axis_mut(axis).emulate_hi_res {
+            // No hi-res wheel on this axis, upconvert the notch so it can be accelerated
+            event.value().saturating_mul(HI_RES_PER_NOTCH)
+        } else {
+            // Drop legacy notches, they are regenerated from the accelerated hi-res events
+            return;
+        };
+        let at = event_time(&event);
+        match state
+            .axis_mut(axis)
+            .debounce
+            .filter(&params.debounce, value, at)
+        {
+            Debounced::Pass => wheel_input(event_batch, axis, value, at, params, state),
+            Debounced::Hold => {}
+            Debounced::Release {
+                value: held,
+                at: held_at,
+            } => {
+                wheel_input(event_batch, axis, held, held_at, params, state);
+                wheel_input(event_batch, axis, value, at, params, state);
+            }
+        }
+    } else {
+        if event.event_type() == EventType::SYNCHRONIZATION
+            && event.code() == SynchronizationCode::SYN_REPORT.0
+        {
+            state.vertical.limiter.end_frame();
+            state.horizontal.limiter.end_frame();
+            // Pointer motion is accelerated a whole frame at a time
+            if let Some((x, y)) = state.pointer.take_frame(&params.pointer) {
+                push_motion(event_batch, x, y);
+            }
+        }
+        // A button press or pointer motion catches a coasting flick
+        if (event.event_type() == EventType::KEY && event.value() == 1)
+            || (event.event_type() == EventType::RELATIVE
+                && (event.code() == RelativeAxisCode::REL_X.0
+                    || event.code() == RelativeAxisCode::REL_Y.0)
+                && event.value() != 0)
+        {
+            state.momentum = None;
+        }
+        match state.drag.handle(&params.drag, &event, event_time(&event)) {
+            DragAction::PassThrough => {}
+            DragAction::Swallow => return,
+            DragAction::Click => {
+                // Press and release in frames of their own, like a real click
+                let button = params.drag.button.0;
+                event_batch.push(InputEvent::new(EventType::KEY.0, button, 1));
+                event_batch.push(InputEvent::new(
+                    EventType::SYNCHRONIZATION.0,
+                    SynchronizationCode::SYN_REPORT.0,
+                    0,
+                ));
+                event_batch.push(InputEvent::new(EventType::KEY.0, button, 0));
+                return;
+            }
+            DragAction::Scroll(axis, value) => {
+                let at = event_time(&event);
+                scroll_wheel(event_batch, axis, value, at, params, state);
+                return;
+            }
+        }
+        if params.pointer.enabled && event.event_type() == EventType::RELATIVE {
+            match RelativeAxisCode(event.code()) {
+                RelativeAxisCode::REL_X => {
+                    state.pointer.add(event.value(), 0, event_time(&event));
+                    return;
+                }
+                RelativeAxisCode::REL_Y => {
+                    state.pointer.add(0, event.value(), event_time(&event));
+                    return;
+                }
+                _ => {}
+            }
+        }
+        if event.event_type() == EventType::KEY {
+            // Mice with extra keys can report keyboard modifiers themselves
+            state.keyboard.update(&event);
+            if state.track_modifier(params, &event) {
+                return;
+            }
+        }
+        // Pass through all other events unchanged
+        event_batch.push(event);
+    }
+}
+
+#[inline(always)]
+/// Process a batch of input events, applying anxious scroll transformation to wheel events
+/// This is a pure function with no I/O dependencies, making it easily testable and benchmarkable
+/// Any [`ScrollCurve`] can be plugged in, including boxed `dyn ScrollCurve`s and [`Curve`].
+pub fn process_events<C: ScrollCurve>(
+    events: impl Iterator<Item = InputEvent>,
+    params: &ScrollParams<C>,
+    state: &mut ScrollState,
+) -> Vec<InputEvent> {
+    let mut event_batch = Vec::new();
+    // The batch is emitted with a SYN_REPORT of its own, so it never continues a frame
+    state.vertical.limiter.end_frame();
+    state.horizontal.limiter.end_frame();
+
+    let mut filtered = Vec::new();
+    for event in events {
+        state.chatter.filter(&params.chatter, event, &mut filtered);
+        for event in filtered.drain(..) {
+            input_event(&mut event_batch, event, params, state);
+        }
+    }
+
+    event_batch
+}
+
+/// When [`tick_events`] next has something to emit, as a monotonic timestamp.
+/// `None` means nothing is pending and the caller can wait for input indefinitely.
+pub fn next_deadline<C>(params: &ScrollParams<C>, state: &ScrollState) -> Option<Duration> {
+    let momentum = state
+        .momentum
+        .as_ref()
+        .map(|momentum| momentum.deadline(&params.kinetic));
+    let vertical = state.vertical.smoother.deadline(&params.smoothing);
+    let horizontal = state.horizontal.smoother.deadline(&params.smoothing);
+    let autoscroll = state
+        .drag
+        .autoscroll
+        .as_ref()
+        .map(|autoscroll| autoscroll.deadline(&params.drag));
+    let debounce = [&state.vertical, &state.horizontal]
+        .map(|axis_state| axis_state.debounce.deadline(&params.debounce));
+    let chatter = state.chatter.deadline(&params.chatter);
+    [momentum, vertical, horizontal, autoscroll, chatter]
+        .into_iter()
+        .chain(debounce)
+        .flatten()
+        .min()
+}
+
+/// Produce the events that are due at `clock.now()` without any input, such as held button presses, smoothed
+/// output, autoscroll and kinetic scrolling frames. Meant to be called whenever [`next_deadline`]
+/// passes.
+pub fn tick_events<C: ScrollCurve, K: Clock + ?Sized>(
+    params: &ScrollParams<C>,
+    state: &mut ScrollState,
+    clock: &K,
+) -> Vec<InputEvent> {
+    let now = clock.now();
+    let mut event_batch = Vec::new();
+    // The batch is emitted with a SYN_REPORT of its own, so it never continues a frame
+    state.vertical.limiter.end_frame();
+    state.horizontal.limiter.end_frame();
+
+    let mut presses = Vec::new();
+    state.chatter.expire(&params.chatter, now, &mut presses);
+    for press in presses {
+        input_event(&mut event_batch, press, params, state);
+    }
+
+    for axis in [Axis::Vertical, Axis::Horizontal] {
+        if let Some((value, at)) = state.axis_mut(axis).debounce.expire(&params.debounce, now) {
+            wheel_input(&mut event_batch, axis, value, at, params, state);
+        }
+    }
+
+    for axis in [Axis::Vertical, Axis::Horizontal] {
+        let axis_state = state.axis_mut(axis);
+        if axis_state
+            .smoother
+            .deadline(&params.smoothing)
+            .is_some_and(|deadline| now >= deadline)
+        {
+            let amount = axis_state.smoother.drain(&params.smoothing, now);
+            if amount != 0 {
+                push_scroll(&mut event_batch, axis, amount, axis_state, params, now);
+            }
+        }
+    }
+
+    if let Some(autoscroll) = &mut state.drag.autoscroll
+        && now >= autoscroll.deadline(&params.drag)
+    {
+        let (horizontal, vertical) = autoscroll.scroll(&params.drag, now);
+        for (axis, amount) in [(Axis::Vertical, vertical), (Axis::Horizontal, horizontal)] {
+            if amount != 0 {
+                push_scroll(
+                    &mut event_batch,
+                    axis,
+                    amount,
+                    state.axis_mut(axis),
+                    params,
+                    now,
+                );
+            }
+        }
+    }
+
+    if let Some(momentum) = &mut state.momentum
+        && now >= momentum.deadline(&params.kinetic)
+    {
+        let axis = momentum.axis;
+        match momentum.coast(&params.kinetic, now) {
+            Some(0) => {}
+            Some(distance) => push_scroll(
+                &mut event_batch,
+                axis,
+                distance,
+                state.axis_mut(axis),
+                params,
+                now,
+            ),
+            None => state.momentum = None,
+        }
+    }
+
+    event_batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use evdev::AttributeSet;
+    use std::time::Duration;
+
+    /// An event stamped with a monotonic timestamp
+    fn event_at(event_type: EventType, code: u16, value: i32, time: Duration) -> InputEvent {
+        InputEvent::from(libc::input_event {
+            time: libc::timeval {
+                tv_sec: time.as_secs() as libc::time_t,
+                tv_usec: time.subsec_micros() as libc::suseconds_t,
+            },
+            type_: event_type.0,
+            code,
+            value,
+        })
+    }
+
+    /// A relative axis event stamped with a monotonic timestamp
+    fn rel_event_at(code: RelativeAxisCode, value: i32, time: Duration) -> InputEvent {
+        event_at(EventType::RELATIVE, code.0, value, time)
+    }
+
+    #[test]
+    fn test_zero_value() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        let result = apply_anxious_scroll(
+            0.0,
+            base_time + Duration::from_millis(10),
+            &params,
+            &mut state,
+        );
+        assert_eq!(result, 0);
+    }
+
+    #[test]
+    fn test_large_value() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        let result = apply_anxious_scroll(
+            1000.0,
+            base_time + Duration::from_millis(1),
+            &params,
+            &mut state,
+        );
+        // Should not panic and should return a reasonable value
+        assert!(result > 0);
+    }
+
+    #[test]
+    fn test_negative_value() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        let result = apply_anxious_scroll(
+            -10.0,
+            base_time + Duration::from_millis(10),
+            &params,
+            &mut state,
+        );
+        assert!(result < 0);
+    }
+
+    #[test]
+    fn test_very_small_elapsed_time() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        // Test with very small elapsed time (1 microsecond)
+        let result = apply_anxious_scroll(
+            10.0,
+            base_time + Duration::from_micros(1),
+            &params,
+            &mut state,
+        );
+        // Intervals below MIN_INTERVAL are clamped to it
+        let mut clamped_state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let clamped =
+            apply_anxious_scroll(10.0, base_time + MIN_INTERVAL, &params, &mut clamped_state);
+        assert!(result > 0);
+        assert_eq!(result, clamped);
+    }
+
+    #[test]
+    fn test_zero_elapsed_time() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        // Two events with the same timestamp read as the fastest possible scroll
+        let result = apply_anxious_scroll(120.0, base_time, &params, &mut state);
+        assert!(result > 0);
+        assert!(result <= (120.0 * params.max_sens) as i32);
+        assert_eq!(state.prev_time, base_time);
+    }
+
+    #[test]
+    fn test_sub_millisecond_resolution() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+
+        let mut fast_state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let fast = apply_anxious_scroll(
+            15.0,
+            base_time + Duration::from_micros(500),
+            &params,
+            &mut fast_state,
+        );
+        let mut slow_state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let slow = apply_anxious_scroll(
+            15.0,
+            base_time + Duration::from_micros(900),
+            &params,
+            &mut slow_state,
+        );
+        // Both intervals used to truncate to 0 ms and produce the same output
+        assert!(fast > slow);
+    }
+
+    #[test]
+    fn test_out_of_order_events() {
+        let params = AnxiousParams::default();
+        let base_time = Duration::from_secs(1000000000);
+        let mut state =
+            AnxiousState::with_clock(&MockClock::new(base_time + Duration::from_millis(100)));
+
+        // Test with out-of-order event (timestamp earlier than prev_time)
+        let result = apply_anxious_scroll(
+            120.0,
+            base_time + Duration::from_millis(50), // Earlier than prev_time
+            &params,
+            &mut state,
+        );
+        // Should not panic and should return a reasonable value
+        // The fallback duration (1000ms) should result in slow scroll behavior
+        assert!(result > 0);
+        // With 1000ms duration, this should behave like a slow scroll (low sensitivity)
+        assert!(result < 2000); // Should be reasonable for slow scroll
+        // Time does not run backwards for the next event
+        assert_eq!(state.prev_time, base_time + Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_parameter_configurations() {
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+
+        // Test default parameters
+        let default_params = AnxiousParams::default();
+        let result1 = apply_anxious_scroll(
+            10.0,
+            base_time + Duration::from_millis(10),
+            &default_params,
+            &mut state,
+        );
+
+        // Test high sensitivity
+        let high_sens_params = AnxiousParams::builder()
+            .max_sens(30.0)
+            .ramp_up_rate(0.5)
+            .build()
+            .unwrap();
+        let result2 = apply_anxious_scroll(
+            10.0,
+            base_time + Duration::from_millis(10),
+            &high_sens_params,
+            &mut state,
+        );
+
+        // Test low sensitivity
+        let low_sens_params = AnxiousParams::new_unchecked(0.5, 5.0, 0.1);
+        let result3 = apply_anxious_scroll(
+            10.0,
+            base_time + Duration::from_millis(10),
+            &low_sens_params,
+            &mut state,
+        );
+
+        // All should return reasonable values
+        assert!(result1 > 0);
+        assert!(result2 > 0);
+        assert!(result3 > 0);
+
+        // High sensitivity should generally produce higher values than low sensitivity
+        assert!(result2 > result3);
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_params() {
+        assert_eq!(
+            AnxiousParams::builder().base_sens(0.0).build(),
+            Err(AnxiousParamsError::BaseSens(0.0))
+        );
+        assert_eq!(
+            AnxiousParams::builder()
+                .base_sens(2.0)
+                .max_sens(1.0)
+                .build(),
+            Err(AnxiousParamsError::MaxSens {
+                base_sens: 2.0,
+                max_sens: 1.0
+            })
+        );
+        assert_eq!(
+            AnxiousParams::builder()
+                .base_sens(2.0)
+                .max_sens(2.0)
+                .build(),
+            Err(AnxiousParamsError::MaxSens {
+                base_sens: 2.0,
+                max_sens: 2.0
+            })
+        );
+        assert!(matches!(
+            AnxiousParams::builder().ramp_up_rate(f32::NAN).build(),
+            Err(AnxiousParamsError::RampUpRate(_))
+        ));
+
+        // What the checks pass is the same curve the raw constructor makes
+        let params = AnxiousParams::builder()
+            .base_sens(2.0)
+            .max_sens(8.0)
+            .build()
+            .unwrap();
+        assert_eq!(params, AnxiousParams::new_unchecked(2.0, 8.0, 0.3));
+        assert!((params.sensitivity(0.0) - 2.0).abs() < 1e-3);
+        assert!(params.check().is_ok());
+
+        // The raw path takes anything, and `check` still tells
+        assert!(
+            AnxiousParams::new_unchecked(0.0, 15.0, 0.3)
+                .check()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_process_events_basic() {
+        use evdev::{EventType, RelativeAxisCode};
+
+        // Create events with proper timestamps to avoid SystemTime issues
+        let base_time = Duration::from_secs(1000000000);
+        let at = base_time + Duration::from_millis(10);
+        let events = [
+            rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, 120, at),
+            rel_event_at(RelativeAxisCode::REL_WHEEL, 1, at), // Should be dropped
+            rel_event_at(RelativeAxisCode::REL_X, 10, at),    // Should pass through
+        ];
+
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        let result = process_events(events.iter().cloned(), &params, &mut state);
+
+        // Should have 3 events: the processed wheel event, the notch regenerated from it
+        // in place of the dropped one, and the pass-through event
+        assert_eq!(result.len(), 3);
+
+        // First event should be the processed wheel event
+        assert_eq!(result[0].event_type(), EventType::RELATIVE);
+        assert_eq!(result[0].code(), RelativeAxisCode::REL_WHEEL_HI_RES.0);
+
+        // Second event should be the regenerated notch
+        assert_eq!(result[1].event_type(), EventType::RELATIVE);
+        assert_eq!(result[1].code(), RelativeAxisCode::REL_WHEEL.0);
+        assert_eq!(result[1].value(), result[0].value() / 120);
+
+        // Third event should be the pass-through event
+        assert_eq!(result[2].event_type(), EventType::RELATIVE);
+        assert_eq!(result[2].code(), RelativeAxisCode::REL_X.0);
+        assert_eq!(result[2].value(), 10);
+    }
+
+    #[test]
+    fn test_curves_start_at_base_sens() {
+        let curves: Vec<Box<dyn ScrollCurve>> = vec![
+            Box::new(AnxiousParams::default()),
+            Box::new(LinearCurve::default()),
+            Box::new(PowerCurve::default()),
+            Box::new(ClassicCurve::default()),
+            Box::new(NaturalCurve::default()),
+        ];
+
+        for curve in &curves {
+            assert!((curve.sensitivity(0.0) - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_curves_are_monotonic_and_capped() {
+        let curves = [
+            Curve::Logistic(AnxiousParams::default()),
+            Curve::Linear(LinearCurve::default()),
+            Curve::Power(PowerCurve::default()),
+            Curve::Classic(ClassicCurve::default()),
+            Curve::Natural(NaturalCurve::default()),
+        ];
+
+        for curve in &curves {
+            let mut prev = curve.sensitivity(0.0);
+            for step in 1..1000 {
+                let sens = curve.sensitivity(step as f32 * 0.1);
+                assert!(sens >= prev - 1e-3, "{curve:?} decreased at step {step}");
+                assert!(
+                    sens <= 15.0 + 1e-3,
+                    "{curve:?} exceeded its cap at step {step}"
+                );
+                prev = sens;
+            }
+        }
+    }
+
+    #[test]
+    fn test_classic_curve_offset() {
+        let curve = ClassicCurve::default();
+        // No acceleration until the offset is crossed
+        assert_eq!(curve.sensitivity(curve.offset), curve.base_sens);
+        assert!(curve.sensitivity(curve.offset + 1.0) > curve.base_sens);
+    }
+
+    #[test]
+    fn test_process_events_with_custom_curve() {
+        use evdev::{EventType, InputEvent, RelativeAxisCode};
+
+        // A curve with no acceleration at all should leave the value unchanged
+        let flat = LinearCurve {
+            base_sens: 1.0,
+            accel: 0.0,
+            max_sens: 1.0,
+        };
+        let base_time = Duration::from_secs(1000000000);
+        let events = [InputEvent::new_now(
+            EventType::RELATIVE.0,
+            RelativeAxisCode::REL_WHEEL_HI_RES.0,
+            120,
+        )];
+
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let params = ScrollParams::both(flat.clone());
+        let result = process_events(events.iter().cloned(), &params, &mut state);
+        assert_eq!(result[0].value(), 120);
+
+        // The same pipeline also accepts runtime-selected curves
+        let dyn_params: ScrollParams<Box<dyn ScrollCurve>> = ScrollParams {
+            vertical: Box::new(Curve::Linear(flat)),
+            horizontal: Box::new(AnxiousParams::default()),
+            ..
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let result = process_events(events.iter().cloned(), &dyn_params, &mut state);
+        assert_eq!(result[0].value(), 120);
+    }
+
+    fn constant_curve(sens: f32) -> LinearCurve {
+        LinearCurve {
+            base_sens: sens,
+            accel: 0.0,
+            max_sens: sens,
+        }
+    }
+
+    #[test]
+    fn test_slow_ticks_add_up_exactly() {
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let curve = constant_curve(0.25);
+
+        // Each tick alone is worth a quarter unit, which used to be truncated to 0
+        let total: i32 = (1..=100)
+            .map(|i| {
+                apply_anxious_scroll(
+                    1.0,
+                    base_time + Duration::from_millis(100 * i),
+                    &curve,
+                    &mut state,
+                )
+            })
+            .sum();
+        assert_eq!(total, 25);
+    }
+
+    #[test]
+    fn test_slow_negative_ticks_add_up_exactly() {
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let curve = constant_curve(0.375);
+
+        let outputs: Vec<i32> = (1..=8)
+            .map(|i| {
+                apply_anxious_scroll(
+                    -3.0,
+                    base_time + Duration::from_millis(100 * i),
+                    &curve,
+                    &mut state,
+                )
+            })
+            .collect();
+        // 8 * -3 * 0.375 = -9, spread as evenly as whole units allow
+        assert_eq!(outputs.iter().sum::<i32>(), -9);
+        assert!(outputs.iter().all(|&out| out == -1 || out == -2));
+    }
+
+    #[test]
+    fn test_remainder_not_carried_across_reversal() {
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let curve = constant_curve(0.75);
+
+        let down = apply_anxious_scroll(
+            1.0,
+            base_time + Duration::from_millis(100),
+            &curve,
+            &mut state,
+        );
+        assert_eq!(down, 0);
+        assert_eq!(state.remainder, 0.75);
+
+        // The pending 0.75 down must not cancel out the first tick up
+        let up = apply_anxious_scroll(
+            -2.0,
+            base_time + Duration::from_millis(200),
+            &curve,
+            &mut state,
+        );
+        assert_eq!(up, -1);
+        assert_eq!(state.remainder, -0.5);
+    }
+
+    #[test]
+    fn test_windowed_velocity_smooths_bursts() {
+        let base_time = Duration::from_secs(1000000000);
+        let window = VelocityParams::Window {
+            window: Duration::from_millis(50),
+        };
+        let params = AnxiousParams::default();
+
+        // Notch pairs delivered in the same millisecond, every 16 ms
+        let burst = |state: &mut AnxiousState| -> Vec<i32> {
+            (0..20)
+                .map(|i| {
+                    let at = base_time + Duration::from_millis(16 * (i / 2 + 1));
+                    apply_anxious_scroll(120.0, at, &params, state)
+                })
+                .collect()
+        };
+
+        let mut instant_state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let instant = burst(&mut instant_state);
+        let mut window_state = AnxiousState {
+            velocity: VelocityEstimator::new(window),
+            ..AnxiousState::with_clock(&MockClock::new(base_time))
+        };
+        let windowed = burst(&mut window_state);
+
+        let spread =
+            |out: &[i32]| out[10..].iter().max().unwrap() - out[10..].iter().min().unwrap();
+        assert!(spread(&windowed) < spread(&instant) / 4);
+    }
+
+    #[test]
+    fn test_simulation_with_mock_clock_is_deterministic() {
+        let params: ScrollParams = ScrollParams::default();
+
+        // Stamp a flick of ten notches 4 ms apart, then a lone notch after a pause, each
+        // in a frame of its own
+        let simulate = || {
+            let clock = MockClock::new(Duration::from_secs(42));
+            let mut state = ScrollState::with_clock(&clock);
+            let mut events = Vec::new();
+            let syn = || {
+                InputEvent::new(
+                    EventType::SYNCHRONIZATION.0,
+                    SynchronizationCode::SYN_REPORT.0,
+                    0,
+                )
+            };
+            for _ in 0..10 {
+                clock.advance(Duration::from_millis(4));
+                events.push(rel_event_at(
+                    RelativeAxisCode::REL_WHEEL_HI_RES,
+                    120,
+                    clock.now(),
+                ));
+                events.push(syn());
+            }
+            clock.advance(Duration::from_millis(500));
+            events.push(rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                clock.now(),
+            ));
+            events.push(syn());
+            process_events(events.into_iter(), &params, &mut state)
+                .iter()
+                .filter(|event| event.code() == RelativeAxisCode::REL_WHEEL_HI_RES.0)
+                .map(|event| event.value())
+                .collect::<Vec<_>>()
+        };
+
+        let first = simulate();
+        assert_eq!(first, simulate());
+        // The flick accelerates, the lone notch after the pause does not
+        assert!(first[9] > 120 * 10);
+        assert!(first[10] < 120 * 2);
+    }
+
+    #[test]
+    fn test_horizontal_wheel_is_accelerated() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            vertical: AnxiousParams::default(),
+            horizontal: AnxiousParams::builder().max_sens(5.0).build().unwrap(),
+            legacy_notches: false,
+            ..
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        let events = [
+            rel_event_at(
+                RelativeAxisCode::REL_HWHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(4),
+            ),
+            rel_event_at(
+                RelativeAxisCode::REL_HWHEEL,
+                1,
+                base_time + Duration::from_millis(4),
+            ),
+            rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(4),
+            ),
+        ];
+        let result = process_events(events.into_iter(), &params, &mut state);
+
+        // The incoming legacy notch is dropped just like on the vertical axis
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].code(), RelativeAxisCode::REL_HWHEEL_HI_RES.0);
+        assert_eq!(result[1].code(), RelativeAxisCode::REL_WHEEL_HI_RES.0);
+        // Both are accelerated, but the horizontal axis by its own, gentler curve
+        assert!(result[0].value() > 120);
+        assert!(result[0].value() <= 120 * 5);
+        assert!(result[1].value() > result[0].value());
+    }
+
+    #[test]
+    fn test_axes_track_velocity_independently() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        // A fast vertical flick...
+        let flick = (1..=10).map(|i| {
+            rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(1000 + 4 * i),
+            )
+        });
+        process_events(flick, &params, &mut state);
+
+        // ...must not make a lone side-scroll tick right after it fast
+        let tick = rel_event_at(
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+            120,
+            base_time + Duration::from_millis(1044),
+        );
+        let result = process_events([tick].into_iter(), &params, &mut state);
+        assert!(result[0].value() < 120 * 2);
+    }
+
+    #[test]
+    fn test_legacy_notches_follow_accelerated_output() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams::both(LinearCurve {
+            base_sens: 1.5,
+            accel: 0.0,
+            max_sens: 1.5,
+        });
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        // Each 120 unit notch comes out as 180 hi-res units, so a legacy notch is
+        // emitted for every 120 units of output: 1, 2, 1, 2, ...
+        let mut notches = Vec::new();
+        for i in 1..=4 {
+            let at = base_time + Duration::from_millis(100 * i);
+            let events = [
+                rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, 120, at),
+                rel_event_at(RelativeAxisCode::REL_WHEEL, 1, at),
+            ];
+            let result = process_events(events.into_iter(), &params, &mut state);
+            assert_eq!(result[0].value(), 180);
+            assert_eq!(result[1].code(), RelativeAxisCode::REL_WHEEL.0);
+            notches.push(result[1].value());
+        }
+        assert_eq!(notches, [1, 2, 1, 2]);
+        assert_eq!(state.vertical.notch_remainder, 0);
+    }
+
+    #[test]
+    fn test_legacy_notches_wait_for_a_full_notch() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams::both(LinearCurve {
+            base_sens: 1.0,
+            accel: 0.0,
+            max_sens: 1.0,
+        });
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        // Hi-res wheels report fractions of a notch; the legacy notch comes on the 4th
+        let mut codes = Vec::new();
+        for i in 1..=4 {
+            let at = base_time + Duration::from_millis(100 * i);
+            let event = rel_event_at(RelativeAxisCode::REL_HWHEEL_HI_RES, -30, at);
+            let result = process_events([event].into_iter(), &params, &mut state);
+            codes.extend(result.iter().map(|event| (event.code(), event.value())));
+        }
+        let hwheel = RelativeAxisCode::REL_HWHEEL_HI_RES.0;
+        assert_eq!(
+            codes,
+            [
+                (hwheel, -30),
+                (hwheel, -30),
+                (hwheel, -30),
+                (hwheel, -30),
+                (RelativeAxisCode::REL_HWHEEL.0, -1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_notch_count_restarts_on_reversal() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams::both(LinearCurve {
+            base_sens: 1.0,
+            accel: 0.0,
+            max_sens: 1.0,
+        });
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        // Most of a notch up, then a full notch back down
+        let mut codes = Vec::new();
+        for (i, value) in [(1, 100), (2, -120)] {
+            let at = base_time + Duration::from_millis(100 * i);
+            let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, value, at);
+            let result = process_events([event].into_iter(), &params, &mut state);
+            codes.extend(result.iter().map(|event| (event.code(), event.value())));
+        }
+        let wheel = RelativeAxisCode::REL_WHEEL_HI_RES.0;
+        assert_eq!(
+            codes,
+            [
+                (wheel, 100),
+                (wheel, -120),
+                (RelativeAxisCode::REL_WHEEL.0, -1),
+            ]
+        );
+        assert_eq!(state.vertical.notch_remainder, 0);
+    }
+
+    #[test]
+    fn test_huge_legacy_notch_is_clamped_not_overflowed() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        state.vertical.emulate_hi_res = true;
+
+        let at = base_time + Duration::from_millis(100);
+        for value in [i32::MAX, i32::MIN] {
+            let event = rel_event_at(RelativeAxisCode::REL_WHEEL, value, at);
+            let result = process_events([event].into_iter(), &params, &mut state);
+            assert_eq!(
+                result[0].value().abs(),
+                params.limits.max_per_event,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_vertical_wheel_only_mouse_is_detected() {
+        let axes: AttributeSet<RelativeAxisCode> = [
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+        ]
+        .into_iter()
+        .collect();
+        assert!(is_wheel_mouse(&axes));
+
+        // Only the vertical hi-res code is added, the missing horizontal wheel stays out
+        assert_eq!(
+            virtual_relative_axes(&axes).iter().collect::<Vec<_>>(),
+            [
+                RelativeAxisCode::REL_X,
+                RelativeAxisCode::REL_Y,
+                RelativeAxisCode::REL_WHEEL,
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+            ]
+        );
+
+        let no_wheel: AttributeSet<RelativeAxisCode> =
+            [RelativeAxisCode::REL_X, RelativeAxisCode::REL_Y]
+                .into_iter()
+                .collect();
+        assert!(!is_wheel_mouse(&no_wheel));
+    }
+
+    #[test]
+    fn test_legacy_only_wheel_is_upconverted() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams::both(LinearCurve {
+            base_sens: 2.0,
+            accel: 0.0,
+            max_sens: 2.0,
+        });
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let axes: AttributeSet<RelativeAxisCode> = [
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+        ]
+        .into_iter()
+        .collect();
+        state.emulate_missing_hi_res(&axes);
+        assert!(state.vertical.emulate_hi_res);
+        assert!(!state.horizontal.emulate_hi_res);
+
+        let at = base_time + Duration::from_millis(100);
+        let events = [
+            rel_event_at(RelativeAxisCode::REL_WHEEL, -1, at),
+            rel_event_at(RelativeAxisCode::REL_HWHEEL_HI_RES, 120, at),
+            rel_event_at(RelativeAxisCode::REL_HWHEEL, 1, at),
+        ];
+        let result: Vec<_> = process_events(events.into_iter(), &params, &mut state)
+            .iter()
+            .map(|event| (event.code(), event.value()))
+            .collect();
+
+        assert_eq!(
+            result,
+            [
+                // The plain notch becomes an accelerated hi-res scroll plus matching notches
+                (RelativeAxisCode::REL_WHEEL_HI_RES.0, -240),
+                (RelativeAxisCode::REL_WHEEL.0, -2),
+                // The horizontal axis has hi-res, so its legacy notch is still replaced
+                (RelativeAxisCode::REL_HWHEEL_HI_RES.0, 240),
+                (RelativeAxisCode::REL_HWHEEL.0, 2),
+            ]
+        );
+    }
+
+    fn kinetic_params() -> ScrollParams {
+        ScrollParams {
+            kinetic: KineticParams { enabled: true, .. },
+            ..ScrollParams::default()
+        }
+    }
+
+    /// Flick the wheel down ten notches, 4 ms apart, ending at `clock.now()`
+    fn flick(clock: &MockClock, params: &ScrollParams, state: &mut ScrollState) {
+        let events: Vec<_> = (0..10)
+            .map(|_| {
+                clock.advance(Duration::from_millis(4));
+                rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, 120, clock.now())
+            })
+            .collect();
+        process_events(events.into_iter(), params, state);
+    }
+
+    #[test]
+    fn test_kinetic_scroll_coasts_after_flick() {
+        let params = kinetic_params();
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let mut state = ScrollState::with_clock(&clock);
+        flick(&clock, &params, &mut state);
+
+        // Nothing happens until the wheel has been quiet for the start delay
+        let start = next_deadline(&params, &state).unwrap();
+        assert_eq!(start, clock.now() + params.kinetic.start_delay);
+        assert!(tick_events(&params, &mut state, &clock).is_empty());
+
+        let mut coasted = Vec::new();
+        while let Some(deadline) = next_deadline(&params, &state) {
+            clock.set(deadline);
+            coasted.extend(
+                tick_events(&params, &mut state, &clock)
+                    .iter()
+                    .filter(|event| event.code() == RelativeAxisCode::REL_WHEEL_HI_RES.0)
+                    .map(|event| event.value()),
+            );
+            assert!(coasted.len() < 1000, "momentum never ran out");
         }
+
+        // The coast continues downwards and slows to a stop
+        assert!(coasted.len() > 10);
+        assert!(coasted.iter().all(|&value| value >= 0));
+        assert!(coasted[0] > *coasted.last().unwrap());
+        assert!(state.momentum.is_none());
     }
-}
 
-/// State for tracking scroll velocity over time
-#[derive(Debug)]
-#[repr(transparent)]
-pub struct AnxiousState {
-    pub prev_time: SystemTime,
-}
+    #[test]
+    fn test_kinetic_scroll_stops_on_input() {
+        let params = kinetic_params();
+        let stoppers = [
+            // Wheel reversal
+            (
+                EventType::RELATIVE,
+                RelativeAxisCode::REL_WHEEL_HI_RES.0,
+                -120,
+            ),
+            // Button press
+            (EventType::KEY, KeyCode::BTN_LEFT.0, 1),
+            // Pointer motion
+            (EventType::RELATIVE, RelativeAxisCode::REL_Y.0, 3),
+        ];
 
-impl AnxiousState {
-    pub fn new() -> Self {
-        Self {
-            prev_time: SystemTime::now(),
+        for (event_type, code, value) in stoppers {
+            let clock = MockClock::new(Duration::from_secs(1000000000));
+            let mut state = ScrollState::with_clock(&clock);
+            flick(&clock, &params, &mut state);
+            clock.set(next_deadline(&params, &state).unwrap());
+            tick_events(&params, &mut state, &clock);
+            assert!(state.momentum.as_ref().unwrap().coasting);
+
+            clock.advance(Duration::from_millis(1));
+            let event = event_at(event_type, code, value, clock.now());
+            process_events([event].into_iter(), &params, &mut state);
+            assert!(
+                state.momentum.is_none(),
+                "{event_type:?} {code} did not stop"
+            );
+            assert_eq!(next_deadline(&params, &state), None);
         }
     }
-}
 
-// Exponential lookup table (LUT) configuration and data
-//
-// The following macro invocation expands at compile time to define:
-// - EXP_LOOKUP_START: f32    (inclusive lower bound)
-// - EXP_LOOKUP_END: f32      (inclusive upper bound)
-// - EXP_LOOKUP_STEPS: usize  (number of samples; must be >= 2)
-// - EXP_LOOKUP_STEP_SIZE: f32
-// - EXP_LOOKUP_LUT: [f32; EXP_LOOKUP_STEPS]  (samples of e^x over [START, END])
-//
-// Inputs must be literals because the proc macro computes the LUT at
-// compile time and emits the array as a literal. Adjust START/END/STEPS
-// here to regenerate the table.
-exp_lut_macro::exp_lut_macro!(
-    start: -12.0,
-    end: 12.0,
-    steps: 10000
-);
+    #[test]
+    fn test_kinetic_scroll_disabled_by_default() {
+        let params: ScrollParams = ScrollParams::default();
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let mut state = ScrollState::with_clock(&clock);
+        flick(&clock, &params, &mut state);
+        assert_eq!(next_deadline(&params, &state), None);
+    }
 
+    #[test]
+    fn test_smoothing_spreads_jump_over_frames() {
+        let params = ScrollParams {
+            smoothing: SmoothingParams { enabled: true, .. },
+            ..ScrollParams::both(LinearCurve {
+                base_sens: 15.0,
+                accel: 0.0,
+                max_sens: 15.0,
+            })
+        };
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let mut state = ScrollState::with_clock(&clock);
 
-#[inline(always)]
-fn fast_exp(x: f32) -> f32 {
-    if x >= EXP_LOOKUP_START && x <= EXP_LOOKUP_END {
-        let index = ((x - EXP_LOOKUP_START) / EXP_LOOKUP_STEP_SIZE) as usize;
-        EXP_LOOKUP_LUT[index]
-    } else {
-        x.exp()
-    }
-}
+        // One notch at max_sens would be a single 1800 unit jump
+        clock.advance(Duration::from_millis(100));
+        let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, 120, clock.now());
+        assert!(process_events([event].into_iter(), &params, &mut state).is_empty());
+        let start = clock.now();
 
+        let mut frames = Vec::new();
+        while let Some(deadline) = next_deadline(&params, &state) {
+            clock.set(deadline);
+            for event in tick_events(&params, &mut state, &clock) {
+                frames.push((clock.now() - start, event.code(), event.value()));
+            }
+            assert!(frames.len() < 1000, "smoothing never finished");
+        }
 
-#[inline(always)]
-/// We use a logistic function as the transformation function.
-/// f(vel) = max_sens / (1 + C * e^(-ramp_up_rate * vel)), where
-/// C = (max_sens / (base_sens) - 1
-/// Visualisation: https://www.desmos.com/calculator/grsgyudrch
-pub fn apply_anxious_scroll(
-    value: f32,
-    timestamp: SystemTime,
-    anxious_params: &AnxiousParams,
-    anxious_state: &mut AnxiousState,
-) -> i32 {
-    let elapsed_time = match timestamp.duration_since(anxious_state.prev_time) {
-        Ok(duration) => duration,
-        Err(_) => {
-            // If timestamp is earlier than prev_time (clock adjustment, out-of-order events, etc.),
-            // use a slow scroll duration (1 second) to treat it as a gentle scroll
-            std::time::Duration::from_millis(1000)
+        let hi_res: Vec<_> = frames
+            .iter()
+            .filter(|(_, code, _)| *code == RelativeAxisCode::REL_WHEEL_HI_RES.0)
+            .collect();
+        let notches: i32 = frames
+            .iter()
+            .filter(|(_, code, _)| *code == RelativeAxisCode::REL_WHEEL.0)
+            .map(|(_, _, value)| value)
+            .sum();
+        // 1 ms apart, ending exactly 60 ms after the notch, adding up to the jump
+        assert_eq!(hi_res.len(), 60);
+        assert_eq!(hi_res[0].0, Duration::from_millis(1));
+        assert_eq!(hi_res.last().unwrap().0, params.smoothing.duration);
+        assert_eq!(hi_res.iter().map(|(_, _, value)| value).sum::<i32>(), 1800);
+        assert_eq!(notches, 15);
+    }
+
+    /// Scroll down fast for `notches` notches 4 ms apart, wait `pause`, then tick once in
+    /// `direction` and return the hi-res output of that last tick
+    fn scroll_then_tick(
+        clock: &MockClock,
+        params: &ScrollParams,
+        state: &mut ScrollState,
+        notches: usize,
+        pause: Duration,
+        direction: i32,
+    ) -> i32 {
+        for _ in 0..notches {
+            clock.advance(Duration::from_millis(4));
+            let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, 120, clock.now());
+            process_events([event].into_iter(), params, state);
         }
-    };
-    anxious_state.prev_time = timestamp;
+        clock.advance(pause);
+        let event = rel_event_at(
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            120 * direction,
+            clock.now(),
+        );
+        process_events([event].into_iter(), params, state)[0].value()
+    }
 
-    let vel = value.abs() / elapsed_time.as_millis() as f32;
-    let c = (anxious_params.max_sens / anxious_params.base_sens) - 1.0;
-    let exp_term = fast_exp(-anxious_params.ramp_up_rate * vel);
-    // Apply the logistic function: max_sens / (1 + c * e^(-ramp_up_rate * vel))
-    let sens = anxious_params.max_sens / (1.0 + c * exp_term);
-    return (value * sens) as i32;
-}
+    #[test]
+    fn test_reversal_resets_velocity() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let pause = Duration::from_millis(4);
 
-#[inline(always)]
-/// Process a batch of input events, applying anxious scroll transformation to wheel events
-/// This is a pure function with no I/O dependencies, making it easily testable and benchmarkable
-pub fn process_events(
-    events: impl Iterator<Item = InputEvent>,
-    anxious_params: &AnxiousParams,
-    anxious_state: &mut AnxiousState,
-) -> Vec<InputEvent> {
-    let mut event_batch = Vec::new();
+        // Overshoot: the correction is accelerated like the fast scroll before it
+        let params = ScrollParams {
+            reset: ResetPolicy {
+                on_reversal: false,
+                ..
+            },
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::with_clock(&clock);
+        let overshoot = scroll_then_tick(&clock, &params, &mut state, 10, pause, -1);
+        assert!(overshoot < -10 * HI_RES_PER_NOTCH, "got {overshoot}");
 
-    for event in events {
-        if event.event_type() == EventType::RELATIVE
-            && event.code() == RelativeAxisCode::REL_WHEEL_HI_RES.0
-        {
-            // Create a new event with modified value
-            let modified_value = apply_anxious_scroll(
-                event.value() as f32,
-                event.timestamp(),
-                anxious_params,
-                anxious_state,
-            );
-            // new_now() is not necessary here as the kernel will update the time field
-            // when it emits the events to any programs reading the event "file".
-            let modified_event =
-                InputEvent::new(event.event_type().0, event.code(), modified_value);
-            event_batch.push(modified_event);
-        } else if event.event_type() == EventType::RELATIVE
-            && event.code() == RelativeAxisCode::REL_WHEEL.0
-        {
-            // Drop event
-            continue;
-        } else {
-            // Pass through all other events unchanged
-            event_batch.push(event);
+        // Correct: the correction starts over at base sensitivity
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = ScrollState::with_clock(&clock);
+        let correction = scroll_then_tick(&clock, &params, &mut state, 10, pause, -1);
+        assert!(correction > -2 * HI_RES_PER_NOTCH, "got {correction}");
+        assert!(correction < 0);
+        assert_eq!(state.vertical.direction, -1.0);
+
+        // Carrying on in the new direction accelerates again
+        clock.advance(Duration::from_millis(4));
+        let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, -120, clock.now());
+        let output = process_events([event].into_iter(), &params, &mut state)[0].value();
+        assert!(output < -10 * HI_RES_PER_NOTCH, "got {output}");
+    }
+
+    /// Scroll state whose velocity estimator remembers the last half second
+    fn slow_ema_state(clock: &MockClock) -> ScrollState {
+        let axis_state = || AnxiousState {
+            velocity: VelocityEstimator::new(VelocityParams::Ema {
+                time_constant: Duration::from_millis(500),
+            }),
+            ..AnxiousState::with_clock(clock)
+        };
+        ScrollState {
+            vertical: axis_state(),
+            horizontal: axis_state(),
+            ..ScrollState::with_clock(clock)
         }
     }
 
-    event_batch
-}
+    #[test]
+    fn test_idle_timeout_resets_velocity() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let pause = Duration::from_millis(150);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::time::{Duration, UNIX_EPOCH};
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = slow_ema_state(&clock);
+        let remembered = scroll_then_tick(&clock, &params, &mut state, 100, pause, 1);
+        assert!(remembered > 5 * HI_RES_PER_NOTCH, "got {remembered}");
 
-    fn create_test_state_with_time(prev_time: SystemTime) -> AnxiousState {
-        AnxiousState { prev_time }
+        let params = ScrollParams {
+            reset: ResetPolicy {
+                idle_timeout: Some(Duration::from_millis(100)),
+                ..
+            },
+            ..ScrollParams::default()
+        };
+        let mut state = slow_ema_state(&clock);
+        let forgotten = scroll_then_tick(&clock, &params, &mut state, 100, pause, 1);
+        assert!(forgotten < 2 * HI_RES_PER_NOTCH, "got {forgotten}");
     }
 
     #[test]
-    fn test_zero_value() {
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time);
-
-        let result = apply_anxious_scroll(
-            0.0,
-            base_time + Duration::from_millis(10),
+    fn test_velocity_decays_between_events() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let params = ScrollParams {
+            reset: ResetPolicy {
+                decay: Some(Duration::from_millis(20)),
+                ..
+            },
+            ..ScrollParams::default()
+        };
+        let mut state = slow_ema_state(&clock);
+        let output = scroll_then_tick(
+            &clock,
             &params,
             &mut state,
+            100,
+            Duration::from_millis(150),
+            1,
         );
-        assert_eq!(result, 0);
+        assert!(output < 2 * HI_RES_PER_NOTCH, "got {output}");
     }
 
     #[test]
-    fn test_large_value() {
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time);
+    fn test_directional_curve_picks_by_sign() {
+        let base_time = Duration::from_secs(1000000000);
+        let mut state = AnxiousState::with_clock(&MockClock::new(base_time));
+        let curve = DirectionalCurve {
+            positive: constant_curve(1.0),
+            negative: constant_curve(3.0),
+        };
 
-        let result = apply_anxious_scroll(
-            1000.0,
-            base_time + Duration::from_millis(1),
-            &params,
+        let up = apply_anxious_scroll(
+            120.0,
+            base_time + Duration::from_millis(100),
+            &curve,
             &mut state,
         );
-        // Should not panic and should return a reasonable value
-        assert!(result > 0);
+        let down = apply_anxious_scroll(
+            -120.0,
+            base_time + Duration::from_millis(200),
+            &curve,
+            &mut state,
+        );
+        assert_eq!(up, 120);
+        assert_eq!(down, -360);
+
+        // Works through process_events and dyn curves too
+        let boxed: Box<dyn ScrollCurve> = Box::new(curve);
+        let params = ScrollParams::both(&*boxed);
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let event = rel_event_at(
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            -120,
+            base_time + Duration::from_millis(100),
+        );
+        let output = process_events([event].into_iter(), &params, &mut state);
+        assert_eq!(output[0].value(), -360);
     }
 
     #[test]
-    fn test_negative_value() {
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time);
+    fn test_huge_output_is_clamped() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams::default();
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
 
-        let result = apply_anxious_scroll(
-            -10.0,
-            base_time + Duration::from_millis(10),
-            &params,
-            &mut state,
+        // A huge value at the same instant as the previous event would saturate i32
+        let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, i32::MAX / 2, base_time);
+        let output = process_events([event].into_iter(), &params, &mut state);
+        assert_eq!(output[0].value(), params.limits.max_per_event);
+        assert_eq!(
+            output[1].value(),
+            params.limits.max_per_event / HI_RES_PER_NOTCH
         );
-        assert!(result < 0);
+
+        let event = rel_event_at(RelativeAxisCode::REL_WHEEL_HI_RES, i32::MIN, base_time);
+        let output = process_events([event].into_iter(), &params, &mut state);
+        assert_eq!(output[0].value(), -params.limits.max_per_event);
     }
 
     #[test]
-    fn test_very_small_elapsed_time() {
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time);
+    fn test_frame_limit_spans_events_until_syn_report() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            limits: OutputLimits {
+                max_per_frame: 3000,
+                ..
+            },
+            legacy_notches: false,
+            ..ScrollParams::both(constant_curve(10.0))
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
 
-        // Test with very small elapsed time (1 microsecond)
-        let result = apply_anxious_scroll(
-            10.0,
-            base_time + Duration::from_micros(1),
-            &params,
-            &mut state,
+        let wheel = |ms| {
+            rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(ms),
+            )
+        };
+        let syn = InputEvent::new(
+            EventType::SYNCHRONIZATION.0,
+            SynchronizationCode::SYN_REPORT.0,
+            0,
         );
-        // Should not panic and should return a reasonable value
-        assert!(result > 0);
+        let events = [wheel(10), wheel(11), wheel(12), syn, wheel(20)];
+        let output = process_events(events.into_iter(), &params, &mut state);
+
+        let values: Vec<_> = output.iter().map(|event| event.value()).collect();
+        // The third event only gets what is left of the frame, the next frame starts over
+        assert_eq!(values, [1200, 1200, 600, 0, 1200]);
+    }
+
+    fn key_event(code: KeyCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::KEY.0, code.0, value)
     }
 
     #[test]
-    fn test_out_of_order_events() {
-        let params = AnxiousParams::default();
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time + Duration::from_millis(100));
+    fn test_modifier_buttons_change_sensitivity_while_held() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            legacy_notches: false,
+            modifiers: vec![
+                ButtonModifier {
+                    button: KeyCode::BTN_SIDE,
+                    mode: ModifierMode::Precision { sens: 0.25 },
+                    ..
+                },
+                ButtonModifier {
+                    button: KeyCode::BTN_EXTRA,
+                    mode: ModifierMode::Boost { multiplier: 3.0 },
+                    ..
+                },
+            ],
+            ..ScrollParams::both(constant_curve(2.0))
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let mut ms = 0;
+        let mut scroll = |state: &mut ScrollState, before: Vec<InputEvent>| {
+            ms += 100;
+            let wheel = rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(ms),
+            );
+            let events = before.into_iter().chain([wheel]);
+            process_events(events, &params, state)
+                .last()
+                .unwrap()
+                .value()
+        };
 
-        // Test with out-of-order event (timestamp earlier than prev_time)
-        let result = apply_anxious_scroll(
-            120.0,
-            base_time + Duration::from_millis(50), // Earlier than prev_time
-            &params,
-            &mut state,
+        assert_eq!(scroll(&mut state, vec![]), 240);
+        assert_eq!(
+            scroll(&mut state, vec![key_event(KeyCode::BTN_SIDE, 1)]),
+            30
+        );
+        // Held buttons stack, the one pressed last wins
+        assert_eq!(
+            scroll(&mut state, vec![key_event(KeyCode::BTN_EXTRA, 1)]),
+            720
+        );
+        assert_eq!(
+            scroll(&mut state, vec![key_event(KeyCode::BTN_EXTRA, 0)]),
+            30
+        );
+        assert_eq!(
+            scroll(&mut state, vec![key_event(KeyCode::BTN_SIDE, 0)]),
+            240
         );
-        // Should not panic and should return a reasonable value
-        // The fallback duration (1000ms) should result in slow scroll behavior
-        assert!(result > 0);
-        // With 1000ms duration, this should behave like a slow scroll (low sensitivity)
-        assert!(result < 2000); // Should be reasonable for slow scroll
     }
 
+    #[test]
+    fn test_swallowed_modifier_is_not_passed_through() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams {
+            modifiers: vec![ButtonModifier {
+                button: KeyCode::BTN_SIDE,
+                mode: ModifierMode::Precision { sens: 1.0 },
+                swallow: true,
+            }],
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        let events = [
+            key_event(KeyCode::BTN_SIDE, 1),
+            key_event(KeyCode::BTN_LEFT, 1),
+            key_event(KeyCode::BTN_LEFT, 0),
+            key_event(KeyCode::BTN_SIDE, 0),
+        ];
+        let output = process_events(events.into_iter(), &params, &mut state);
+        let codes: Vec<_> = output.iter().map(|event| event.code()).collect();
+        assert_eq!(codes, [KeyCode::BTN_LEFT.0, KeyCode::BTN_LEFT.0]);
+        assert!(state.held_modifiers.is_empty());
+    }
 
     #[test]
-    fn test_parameter_configurations() {
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let mut state = create_test_state_with_time(base_time);
+    fn test_keyboard_modifiers_change_the_wheel() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            legacy_notches: false,
+            ..ScrollParams::both(constant_curve(2.0))
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let wheel = |ms| {
+            rel_event_at(
+                RelativeAxisCode::REL_WHEEL_HI_RES,
+                120,
+                base_time + Duration::from_millis(ms),
+            )
+        };
 
-        // Test default parameters
-        let default_params = AnxiousParams::default();
-        let result1 = apply_anxious_scroll(
-            10.0,
-            base_time + Duration::from_millis(10),
-            &default_params,
-            &mut state,
-        );
+        // Shift held on a monitored keyboard turns the wheel sideways
+        state.keyboard.update(&key_event(KeyCode::KEY_LEFTSHIFT, 1));
+        let output = process_events([wheel(100)].into_iter(), &params, &mut state);
+        assert_eq!(output[0].code(), RelativeAxisCode::REL_HWHEEL_HI_RES.0);
+        assert_eq!(output[0].value(), 240);
+        state.keyboard.update(&key_event(KeyCode::KEY_LEFTSHIFT, 0));
 
-        // Test high sensitivity
-        let high_sens_params = AnxiousParams {
-            base_sens: 1.0,
-            max_sens: 30.0,
-            ramp_up_rate: 0.5,
+        // Alt scrolls precisely
+        state.keyboard.update(&key_event(KeyCode::KEY_RIGHTALT, 1));
+        let output = process_events([wheel(200)].into_iter(), &params, &mut state);
+        assert_eq!(output[0].code(), RelativeAxisCode::REL_WHEEL_HI_RES.0);
+        assert_eq!(output[0].value(), 30);
+        state.keyboard.update(&key_event(KeyCode::KEY_RIGHTALT, 0));
+
+        let output = process_events([wheel(300)].into_iter(), &params, &mut state);
+        assert_eq!(output[0].value(), 240);
+    }
+
+    #[test]
+    fn test_middle_drag_scrolls_instead_of_moving() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            drag: DragParams { enabled: true, .. },
+            legacy_notches: false,
+            ..ScrollParams::both(constant_curve(2.0))
         };
-        let result2 = apply_anxious_scroll(
-            10.0,
-            base_time + Duration::from_millis(10),
-            &high_sens_params,
-            &mut state,
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let motion =
+            |code, value, ms| rel_event_at(code, value, base_time + Duration::from_millis(ms));
+
+        let events = [
+            key_event(KeyCode::BTN_MIDDLE, 1),
+            motion(RelativeAxisCode::REL_Y, 3, 100),
+            motion(RelativeAxisCode::REL_Y, 10, 110),
+            motion(RelativeAxisCode::REL_X, -10, 110),
+            key_event(KeyCode::BTN_MIDDLE, 0),
+            motion(RelativeAxisCode::REL_Y, 10, 120),
+        ];
+        let output: Vec<_> = process_events(events.into_iter(), &params, &mut state)
+            .iter()
+            .map(|event| (event.code(), event.value()))
+            .collect();
+
+        // The first 3 pixels are within the click slop, the rest scroll at 4 units per
+        // pixel times the curve, and the pointer only moves again after the release
+        assert_eq!(
+            output,
+            [
+                (RelativeAxisCode::REL_WHEEL_HI_RES.0, -80),
+                (RelativeAxisCode::REL_HWHEEL_HI_RES.0, -80),
+                (RelativeAxisCode::REL_Y.0, 10),
+            ]
         );
+    }
 
-        // Test low sensitivity
-        let low_sens_params = AnxiousParams {
-            base_sens: 0.5,
-            max_sens: 5.0,
-            ramp_up_rate: 0.1,
+    #[test]
+    fn test_autoscroll_runs_from_the_timer() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let params: ScrollParams = ScrollParams {
+            drag: DragParams {
+                enabled: true,
+                autoscroll: true,
+                ..
+            },
+            legacy_notches: false,
+            ..ScrollParams::default()
         };
-        let result3 = apply_anxious_scroll(
-            10.0,
-            base_time + Duration::from_millis(10),
-            &low_sens_params,
+        let mut state = ScrollState::with_clock(&clock);
+
+        // A click anchors autoscroll, moving the pointer up sets it going
+        let button = |value| event_at(EventType::KEY, KeyCode::BTN_MIDDLE.0, value, clock.now());
+        let events = [
+            button(1),
+            button(0),
+            rel_event_at(RelativeAxisCode::REL_Y, -28, clock.now()),
+        ];
+        assert!(process_events(events.into_iter(), &params, &mut state).is_empty());
+
+        let mut total = 0;
+        for _ in 0..10 {
+            clock.set(next_deadline(&params, &state).unwrap());
+            for event in tick_events(&params, &mut state, &clock) {
+                assert_eq!(event.code(), RelativeAxisCode::REL_WHEEL_HI_RES.0);
+                total += event.value();
+            }
+        }
+        // 20 pixels past the dead zone is 1 unit per millisecond, for 80 ms
+        assert_eq!(total, 80);
+
+        // Any other click stops it and goes through
+        let output = process_events(
+            [key_event(KeyCode::BTN_LEFT, 1)].into_iter(),
+            &params,
             &mut state,
         );
+        assert_eq!(output.len(), 1);
+        assert_eq!(next_deadline(&params, &state), None);
+    }
 
-        // All should return reasonable values
-        assert!(result1 > 0);
-        assert!(result2 > 0);
-        assert!(result3 > 0);
+    #[test]
+    fn test_reconfigure_stops_autoscroll_of_disabled_drag() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let mut params: ScrollParams = ScrollParams {
+            drag: DragParams {
+                enabled: true,
+                autoscroll: true,
+                ..
+            },
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::with_clock(&clock);
 
-        // High sensitivity should generally produce higher values than low sensitivity
-        assert!(result2 > result3);
+        let button = |value| event_at(EventType::KEY, KeyCode::BTN_MIDDLE.0, value, clock.now());
+        let events = [
+            button(1),
+            button(0),
+            rel_event_at(RelativeAxisCode::REL_Y, -28, clock.now()),
+        ];
+        process_events(events.into_iter(), &params, &mut state);
+        assert!(next_deadline(&params, &state).is_some());
+
+        // A reload turns drag scrolling off while autoscroll is running
+        params.drag.enabled = false;
+        state.reconfigure(&params);
+        assert_eq!(next_deadline(&params, &state), None);
+        assert!(tick_events(&params, &mut state, &clock).is_empty());
     }
 
     #[test]
-    fn test_process_events_basic() {
-        use evdev::{EventType, InputEvent, RelativeAxisCode};
+    fn test_pointer_motion_is_accelerated_per_frame() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams {
+            pointer: PointerParams {
+                enabled: true,
+                curve: Curve::Linear(constant_curve(2.0)),
+            },
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let at = base_time + Duration::from_millis(8);
+        let syn = event_at(
+            EventType::SYNCHRONIZATION,
+            SynchronizationCode::SYN_REPORT.0,
+            0,
+            at,
+        );
 
-        // Create events with proper timestamps to avoid SystemTime issues
-        let base_time = UNIX_EPOCH + Duration::from_secs(1000000000);
-        let events = vec![
-            InputEvent::new_now(
-                EventType::RELATIVE.0,
-                RelativeAxisCode::REL_WHEEL_HI_RES.0,
-                120,
-            ),
-            InputEvent::new_now(EventType::RELATIVE.0, RelativeAxisCode::REL_WHEEL.0, 1), // Should be dropped
-            InputEvent::new_now(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, 10), // Should pass through
+        let events = [
+            rel_event_at(RelativeAxisCode::REL_X, 3, at),
+            rel_event_at(RelativeAxisCode::REL_Y, -2, at),
+            key_event(KeyCode::BTN_LEFT, 1),
+            syn,
         ];
+        let output: Vec<_> = process_events(events.into_iter(), &params, &mut state)
+            .iter()
+            .map(|event| (event.event_type(), event.code(), event.value()))
+            .collect();
 
-        let params = AnxiousParams::default();
-        let mut state = create_test_state_with_time(base_time);
+        // Motion is held back until the frame ends, then comes out just before its
+        // SYN_REPORT
+        assert_eq!(
+            output,
+            [
+                (EventType::KEY, KeyCode::BTN_LEFT.0, 1),
+                (EventType::RELATIVE, RelativeAxisCode::REL_X.0, 6),
+                (EventType::RELATIVE, RelativeAxisCode::REL_Y.0, -4),
+                (
+                    EventType::SYNCHRONIZATION,
+                    SynchronizationCode::SYN_REPORT.0,
+                    0
+                ),
+            ]
+        );
+    }
 
-        let result = process_events(events.iter().cloned(), &params, &mut state);
+    #[test]
+    fn test_axis_mapping_applies_to_hi_res_and_legacy() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            mapping: AxisMapping {
+                swap: true,
+                invert_horizontal: true,
+                ..
+            },
+            ..ScrollParams::both(constant_curve(1.0))
+        };
+        let wheel = |code, value| rel_event_at(code, value, base_time + Duration::from_millis(100));
+        let output = |state: &mut ScrollState, event| -> Vec<_> {
+            process_events([event].into_iter(), &params, state)
+                .iter()
+                .map(|event| (event.code(), event.value()))
+                .collect()
+        };
 
-        // Should have 2 events: one processed wheel event and one pass-through event
-        assert_eq!(result.len(), 2);
+        // The vertical hi-res wheel comes out horizontal and reversed, with a matching notch
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        assert_eq!(
+            output(&mut state, wheel(RelativeAxisCode::REL_WHEEL_HI_RES, 120)),
+            [
+                (RelativeAxisCode::REL_HWHEEL_HI_RES.0, -120),
+                (RelativeAxisCode::REL_HWHEEL.0, -1),
+            ]
+        );
 
-        // First event should be the processed wheel event
-        assert_eq!(result[0].event_type(), EventType::RELATIVE);
-        assert_eq!(result[0].code(), RelativeAxisCode::REL_WHEEL_HI_RES.0);
+        // So does a legacy-only wheel, and the horizontal wheel is not inverted
+        let mut axes = AttributeSet::<RelativeAxisCode>::new();
+        axes.insert(RelativeAxisCode::REL_WHEEL);
+        axes.insert(RelativeAxisCode::REL_HWHEEL);
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        state.emulate_missing_hi_res(&axes);
+        assert_eq!(
+            output(&mut state, wheel(RelativeAxisCode::REL_WHEEL, 1)),
+            [
+                (RelativeAxisCode::REL_HWHEEL_HI_RES.0, -120),
+                (RelativeAxisCode::REL_HWHEEL.0, -1),
+            ]
+        );
+        assert_eq!(
+            output(&mut state, wheel(RelativeAxisCode::REL_HWHEEL, -1)),
+            [
+                (RelativeAxisCode::REL_WHEEL_HI_RES.0, -120),
+                (RelativeAxisCode::REL_WHEEL.0, -1),
+            ]
+        );
+    }
 
-        // Second event should be the pass-through event
-        assert_eq!(result[1].event_type(), EventType::RELATIVE);
-        assert_eq!(result[1].code(), RelativeAxisCode::REL_X.0);
-        assert_eq!(result[1].value(), 10);
+    /// A fast scroll down, 8 ms per notch, with `glitch` ticks up at the given notches
+    fn worn_wheel_trace(base_time: Duration, glitches: &[usize]) -> Vec<InputEvent> {
+        (0..12)
+            .map(|notch| {
+                let value = if glitches.contains(&notch) { 120 } else { -120 };
+                rel_event_at(
+                    RelativeAxisCode::REL_WHEEL_HI_RES,
+                    value,
+                    base_time + Duration::from_millis(8 * notch as u64),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_debounce_drops_worn_wheel_glitches() {
+        let base_time = Duration::from_secs(1000000000);
+        let params = ScrollParams {
+            debounce: DebounceParams { enabled: true, .. },
+            legacy_notches: false,
+            ..ScrollParams::both(constant_curve(1.0))
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+
+        let trace = worn_wheel_trace(base_time, &[4, 9]);
+        let output = process_events(trace.into_iter(), &params, &mut state);
+        assert!(output.iter().all(|event| event.value() < 0));
+        assert_eq!(output.len(), 10);
+        assert_eq!(state.vertical.debounce.filtered, 2);
+        assert_eq!(next_deadline(&params, &state), None);
+
+        // Without debouncing the page jitters back
+        let params = ScrollParams {
+            debounce: DebounceParams { .. },
+            ..params
+        };
+        let mut state = ScrollState::with_clock(&MockClock::new(base_time));
+        let trace = worn_wheel_trace(base_time, &[4, 9]);
+        let output = process_events(trace.into_iter(), &params, &mut state);
+        assert_eq!(output.iter().filter(|event| event.value() > 0).count(), 2);
+    }
+
+    #[test]
+    fn test_debounced_correction_is_released_by_the_timer() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let params = ScrollParams {
+            debounce: DebounceParams { enabled: true, .. },
+            legacy_notches: false,
+            ..ScrollParams::both(constant_curve(1.0))
+        };
+        let mut state = ScrollState::with_clock(&clock);
+
+        // The scroll ends on a single tick back, which is held for now
+        let trace = worn_wheel_trace(clock.now(), &[11]);
+        let output = process_events(trace.into_iter(), &params, &mut state);
+        assert_eq!(output.len(), 11);
+
+        clock.set(next_deadline(&params, &state).unwrap());
+        let output = tick_events(&params, &mut state, &clock);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].value(), 120);
+        assert_eq!(state.vertical.debounce.released, 1);
+    }
+
+    #[test]
+    fn test_chattering_button_clicks_once() {
+        let base_time = Duration::from_secs(1000000000);
+        let params: ScrollParams = ScrollParams {
+            chatter: ChatterParams { enabled: true, .. },
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::new();
+
+        // One click, then the switch bounces 12 ms after letting go
+        let button = |value, ms| {
+            event_at(
+                EventType::KEY,
+                KeyCode::BTN_LEFT.0,
+                value,
+                base_time + Duration::from_millis(ms),
+            )
+        };
+        let events = [button(1, 0), button(0, 90), button(1, 102), button(0, 104)];
+        let output = process_events(events.into_iter(), &params, &mut state);
+
+        let values: Vec<_> = output.iter().map(|event| event.value()).collect();
+        assert_eq!(values, [1, 0]);
+        assert_eq!(state.chatter.suppressed(), 2);
+    }
+
+    #[test]
+    fn test_held_press_is_released_by_the_timer() {
+        let clock = MockClock::new(Duration::from_secs(1000000000));
+        let params: ScrollParams = ScrollParams {
+            chatter: ChatterParams {
+                enabled: true,
+                default: ChatterThresholds {
+                    min_press: Duration::from_millis(8),
+                    ..
+                },
+                ..
+            },
+            modifiers: vec![ButtonModifier {
+                button: KeyCode::BTN_SIDE,
+                mode: ModifierMode::Precision { sens: 0.5 },
+                swallow: false,
+            }],
+            ..ScrollParams::default()
+        };
+        let mut state = ScrollState::with_clock(&clock);
+
+        let press = event_at(EventType::KEY, KeyCode::BTN_SIDE.0, 1, clock.now());
+        assert!(process_events([press].into_iter(), &params, &mut state).is_empty());
+        assert!(state.active_modifier(&params).is_none());
+
+        // Once it has lasted long enough the press goes through, and takes effect
+        clock.set(next_deadline(&params, &state).unwrap());
+        let output = tick_events(&params, &mut state, &clock);
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].code(), KeyCode::BTN_SIDE.0);
+        assert!(state.active_modifier(&params).is_some());
     }
 }
@@ -0,0 +1,143 @@
+use crate::{Axis, HI_RES_PER_NOTCH};
+use log::debug;
+use std::time::Duration;
+
+/// Safety bounds on the accelerated output of one wheel axis, in hi-res units
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutputLimits {
+    /// Most a single output event may scroll
+    pub max_per_event: i32 = 30 * HI_RES_PER_NOTCH,
+    /// Most all output events between two `SYN_REPORT`s may scroll together
+    pub max_per_frame: i32 = 60 * HI_RES_PER_NOTCH,
+    /// Most the output may scroll in any second. The budget refills continuously.
+    pub max_per_second: i32 = 2000 * HI_RES_PER_NOTCH,
+}
+
+/// Tracks how much of the frame and per-second budget one axis has used
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputLimiter {
+    /// Output emitted since the last `SYN_REPORT`
+    pub frame_total: i32,
+    /// Output that may still be emitted before the per-second limit kicks in
+    pub budget: f32,
+    /// Monotonic time the budget was last refilled, `None` before the first output
+    pub updated: Option<Duration>,
+}
+
+impl OutputLimiter {
+    pub const fn new() -> Self {
+        Self {
+            frame_total: 0,
+            budget: 0.0,
+            updated: None,
+        }
+    }
+
+    /// Start a new frame
+    pub fn end_frame(&mut self) {
+        self.frame_total = 0;
+    }
+
+    /// Clamp an output of `value` on `axis` at `at` to whatever the limits still allow
+    pub fn limit(&mut self, limits: &OutputLimits, axis: Axis, value: i32, at: Duration) -> i32 {
+        let per_second = limits.max_per_second as f32;
+        self.budget = match self.updated {
+            Some(updated) => {
+                let elapsed = at.saturating_sub(updated).as_secs_f32();
+                (self.budget + elapsed * per_second).min(per_second)
+            }
+            None => per_second,
+        };
+        self.updated = Some(self.updated.map_or(at, |updated| updated.max(at)));
+
+        let allowed = limits
+            .max_per_event
+            .min(limits.max_per_frame - self.frame_total)
+            .min(self.budget as i32)
+            .max(0);
+        let magnitude = value.unsigned_abs().min(allowed as u32) as i32;
+        if magnitude as u32 != value.unsigned_abs() {
+            debug!(
+                "Clamped {axis:?} scroll output from {value} to {}",
+                value.signum() * magnitude
+            );
+        }
+
+        self.frame_total += magnitude;
+        self.budget -= magnitude as f32;
+        value.signum() * magnitude
+    }
+}
+
+impl Default for OutputLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_event_limit_keeps_sign() {
+        let limits = OutputLimits::default();
+        let mut limiter = OutputLimiter::new();
+        let max = limits.max_per_event;
+
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 120, START), 120);
+        limiter.end_frame();
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, i32::MAX, START), max);
+        limiter.end_frame();
+        assert_eq!(
+            limiter.limit(&limits, Axis::Vertical, i32::MIN, START),
+            -max
+        );
+    }
+
+    #[test]
+    fn test_frame_limit_resets_on_new_frame() {
+        let limits = OutputLimits {
+            max_per_event: 1000,
+            max_per_frame: 1500,
+            ..
+        };
+        let mut limiter = OutputLimiter::new();
+
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 1000, START), 1000);
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, -1000, START), -500);
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 1000, START), 0);
+
+        limiter.end_frame();
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 1000, START), 1000);
+    }
+
+    #[test]
+    fn test_second_limit_refills_over_time() {
+        let limits = OutputLimits {
+            max_per_second: 10_000,
+            ..
+        };
+        let mut limiter = OutputLimiter::new();
+
+        // Full events drain the budget
+        for _ in 0..5 {
+            limiter.end_frame();
+            limiter.limit(&limits, Axis::Vertical, i32::MAX, START);
+        }
+        assert_eq!(limiter.budget, 0.0);
+        limiter.end_frame();
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 120, START), 0);
+
+        // A quarter of a second later a quarter of the budget is back
+        let later = START + Duration::from_millis(250);
+        limiter.end_frame();
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 5000, later), 2500);
+
+        // Events that arrive out of order do not refill anything
+        limiter.end_frame();
+        assert_eq!(limiter.limit(&limits, Axis::Vertical, 120, START), 0);
+    }
+}
@@ -1,13 +1,57 @@
 use anyhow::{Context, Result};
 use clap::Parser;
-use evdev::{Device, EventType, RelativeAxisCode, uinput::VirtualDevice};
-use log::{error, info};
-use mouse_scroll_daemon::{AnxiousParams, AnxiousState, process_events};
-use std::path::PathBuf;
+use evdev::{Device, EventType, KeyCode, uinput::VirtualDevice};
+use log::{debug, error, info, warn};
+use mouse_scroll_daemon::config::{DEFAULT_CONFIG_PATH, EXAMPLE_CONFIG};
+use mouse_scroll_daemon::{
+    Config, Curve, DeviceInfo, DirectionalCurve, MonotonicClock, Override, ScrollParams,
+    ScrollState, is_wheel_mouse, next_deadline, process_events, tick_events, virtual_relative_axes,
+};
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::signalfd::{SfdFlags, SignalFd};
+use nix::sys::time::TimeSpec;
+use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
+use std::os::fd::{AsFd, AsRawFd};
+use std::path::{Path, PathBuf};
+
+/// Configuration keys of the four wheel curves, which the curve flags set together
+const CURVE_KEYS: [&str; 4] = [
+    "scroll.vertical.positive",
+    "scroll.vertical.negative",
+    "scroll.horizontal.positive",
+    "scroll.horizontal.negative",
+];
+
+/// Name of the virtual mouse, so the daemon never mistakes it for a physical device
+const VIRTUAL_DEVICE_NAME: &str = "Anxious Scroll Daemon";
+
+// EVIOCSCLOCKID: select the clock used to timestamp events on an evdev fd
+nix::ioctl_write_ptr!(eviocsclockid, b'E', 0xa0, libc::c_int);
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    /// Configuration file, instead of /etc/anxious-scroll-daemon/config.toml. Flags
+    /// override what it sets.
+    #[arg(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print the default configuration, with every key documented, and exit
+    #[arg(long)]
+    print_default_config: bool,
+
+    /// Print the configuration with all flags applied and exit. With --device, the
+    /// profile that matches it is applied too.
+    #[arg(long)]
+    print_effective_config: bool,
+
+    /// Set any configuration key, e.g. scroll.kinetic.enabled=true. Can be repeated.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<Override>,
+
     /// Path to the physical mouse device (e.g., /dev/input/event3)
     #[arg(short = 'D', long)]
     device: Option<PathBuf>,
@@ -15,28 +59,160 @@ struct Args {
     /// Enable debug logging
     #[arg(short, long)]
     debug: bool,
+
+    /// Acceleration curve of both wheels, starting from its default parameters
+    #[arg(long, value_name = "TYPE", value_parser = ["logistic", "linear", "power", "classic", "natural"])]
+    curve: Option<String>,
+
+    /// Sensitivity of a slow scroll
+    #[arg(long)]
+    base_sens: Option<f32>,
+
+    /// Sensitivity of a fast scroll, which the curve tops out at
+    #[arg(long)]
+    max_sens: Option<f32>,
+
+    /// How fast the sensitivity ramps up with wheel speed (logistic curve)
+    #[arg(long)]
+    ramp_up_rate: Option<f32>,
+
+    /// Sensitivity gained per unit of wheel speed (linear and classic curves)
+    #[arg(long)]
+    accel: Option<f32>,
+
+    /// Wheel speed scale (power curve)
+    #[arg(long)]
+    scale: Option<f32>,
+
+    /// Exponent of the curve (power curve)
+    #[arg(long)]
+    exponent: Option<f32>,
+
+    /// Wheel speed below which nothing is accelerated (classic and natural curves)
+    #[arg(long)]
+    offset: Option<f32>,
+
+    /// How fast the sensitivity approaches its maximum (natural curve)
+    #[arg(long)]
+    decay_rate: Option<f32>,
+
+    /// Max sensitivity when scrolling up or right
+    #[arg(long)]
+    max_sens_up: Option<f32>,
+
+    /// Max sensitivity when scrolling down or left
+    #[arg(long)]
+    max_sens_down: Option<f32>,
+
+    /// Watch keyboards for Shift (horizontal scrolling) and Alt (precision scrolling)
+    #[arg(short = 'k', long)]
+    keyboard_modifiers: bool,
+
+    /// Path to a keyboard device to watch, instead of searching for them. Can be repeated.
+    #[arg(long = "keyboard", value_name = "PATH")]
+    keyboards: Vec<PathBuf>,
+
+    /// Scroll by moving the mouse with the middle button held
+    #[arg(long)]
+    drag_scroll: bool,
+
+    /// Start autoscroll with a middle click, implies --drag-scroll
+    #[arg(long)]
+    autoscroll: bool,
+
+    /// Accelerate pointer motion too
+    #[arg(long)]
+    pointer_accel: bool,
+
+    /// Reverse the vertical wheel ("natural" scrolling)
+    #[arg(long)]
+    invert_vertical: bool,
+
+    /// Reverse the horizontal wheel
+    #[arg(long)]
+    invert_horizontal: bool,
+
+    /// Exchange the vertical and horizontal wheels
+    #[arg(long)]
+    swap_axes: bool,
+
+    /// Drop the lone reverse ticks worn wheels produce in the middle of a fast scroll
+    #[arg(long)]
+    debounce_wheel: bool,
+
+    /// Drop the phantom double-clicks of worn button switches
+    #[arg(long)]
+    debounce_buttons: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
+    if args.print_default_config {
+        print!("{EXAMPLE_CONFIG}");
+        return Ok(());
+    }
+
     // Initialize logging
     let log_level = if args.debug { "debug" } else { "info" };
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level)).init();
 
+    if args.print_effective_config {
+        let device = match &args.device {
+            Some(path) => {
+                let device = Device::open(path).context("Failed to open specified device")?;
+                Some(device_info(&device, path))
+            }
+            None => None,
+        };
+        print!("{}", effective_config(&args, device.as_ref())?.to_toml());
+        return Ok(());
+    }
+
     info!("Starting anxious scroll daemon");
 
-    // Initialize anxious parameters and state
-    let anxious_params = AnxiousParams::default();
-    // TODO: analyse initial jitter?
-    let mut anxious_state = AnxiousState::new();
+    // Set up before loading, so no change made in between goes unnoticed
+    let mut config_watcher = ConfigWatcher::new(
+        args.config
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH)),
+    )?;
+    // Read once, so the settings the device is picked with are the ones it runs with
+    let file_config = load_config(args.config.as_deref())?;
+    let config = configure(&file_config, &args, None)?;
 
     // Find the physical mouse device
-    let mut physical_device = find_mouse_device(args.device)?;
+    let (mut physical_device, device_path) = find_mouse_device(config.device.path)?;
     info!(
         "Found physical mouse: {}",
         physical_device.name().unwrap_or("Unknown")
     );
+    let device = device_info(&physical_device, &device_path);
+    info!(
+        "Mouse bus 0x{:04x}, vendor 0x{:04x}, product 0x{:04x}, phys {}",
+        device.bus,
+        device.vendor,
+        device.product,
+        device.phys.as_deref().unwrap_or("unknown")
+    );
+    debug!("Mouse /dev/input/by-id links: {:?}", device.by_id);
+
+    // Initialize anxious parameters and state, from the profile for this mouse if any
+    let config = configure(&file_config, &args, Some(&device))?;
+    let mut scroll_params = config.scroll;
+    // TODO: analyse initial jitter?
+    let mut scroll_state = ScrollState::with_params(&scroll_params);
+
+    // Timestamp events on the monotonic clock so wall-clock jumps can't skew velocity
+    use_monotonic_clock(&physical_device)?;
+
+    // Accelerate plain wheels too, by upconverting their notches to hi-res units
+    if let Some(relative_axes) = physical_device.supported_relative_axes() {
+        scroll_state.emulate_missing_hi_res(relative_axes);
+        if scroll_state.vertical.emulate_hi_res || scroll_state.horizontal.emulate_hi_res {
+            info!("Device has no hi-res wheel, emulating hi-res scrolling");
+        }
+    }
 
     // Create virtual mouse device
     let mut virtual_device = create_virtual_mouse(&physical_device)?;
@@ -48,6 +224,13 @@ fn main() -> Result<()> {
         info!("Virtual device available at: {}", path.display());
     }
 
+    // Keyboards are only read to follow their modifier keys, never grabbed
+    let mut keyboards = if config.device.keyboard_modifiers || !config.device.keyboards.is_empty() {
+        find_keyboard_devices(config.device.keyboards)?
+    } else {
+        Vec::new()
+    };
+
     // Grab the physical device to get exclusive access
     physical_device
         .grab()
@@ -58,18 +241,279 @@ fn main() -> Result<()> {
     info!("Starting event pass-through loop...");
     run_pass_through_loop(
         &mut physical_device,
+        &mut keyboards,
         &mut virtual_device,
-        &anxious_params,
-        &mut anxious_state,
+        &mut config_watcher,
+        || effective_config(&args, Some(&device)),
+        &mut scroll_params,
+        &mut scroll_state,
     )?;
 
     Ok(())
 }
 
-fn find_mouse_device(device_path: Option<PathBuf>) -> Result<Device> {
+/// The configuration file with the profile for `device`, then the flags applied on top
+fn effective_config(args: &Args, device: Option<&DeviceInfo>) -> Result<Config> {
+    configure(&load_config(args.config.as_deref())?, args, device)
+}
+
+/// The configuration loaded from the file with the profile for `device`, then the
+/// flags applied on top
+fn configure(config: &Config, args: &Args, device: Option<&DeviceInfo>) -> Result<Config> {
+    let mut config = config.clone();
+    if let Some(device) = device {
+        match config.profile_for(device) {
+            Some(profile) => {
+                info!("Using profile `{}` for {}", profile.name, device.name);
+                config = config.with_profile(profile)?;
+            }
+            None if !config.profiles.is_empty() => {
+                info!(
+                    "No profile matches {}, using the top-level settings",
+                    device.name
+                );
+            }
+            None => {}
+        }
+    }
+    // The curve parameters go where the curve types they set, so those come first
+    let config = config
+        .with_overrides(&curve_type_overrides(args))
+        .context("Invalid command-line flags")?;
+    let mut overrides = curve_param_overrides(args, &config)?;
+    overrides.extend(args.overrides.iter().cloned());
+    let mut config = config
+        .with_overrides(&overrides)
+        .context("Invalid command-line flags")?;
+    apply_flags(&mut config, args);
+    config.validate().context("Invalid command-line flags")?;
+    Ok(config)
+}
+
+/// `--curve`, which replaces every wheel curve with one of the new type
+fn curve_type_overrides(args: &Args) -> Vec<Override> {
+    let Some(curve) = &args.curve else {
+        return Vec::new();
+    };
+    let table = toml::Table::from_iter([("type".to_owned(), curve.as_str().into())]);
+    CURVE_KEYS
+        .iter()
+        .map(|key| Override::new(*key, table.clone()))
+        .collect()
+}
+
+/// The curve parameter flags, each set on only the wheel curves of `config` whose type
+/// takes it. The curves can be of different types, e.g. per direction, so `--scale`
+/// sets the power curves and leaves a linear one alone.
+fn curve_param_overrides(args: &Args, config: &Config) -> Result<Vec<Override>> {
+    let table =
+        toml::Table::try_from(config).expect("configuration is always representable as TOML");
+    let curve_at = |key: &str| {
+        key.split('.')
+            .try_fold(&table, |table, part| table.get(part)?.as_table())
+    };
+    let params = [
+        ("base_sens", args.base_sens),
+        ("ramp_up_rate", args.ramp_up_rate),
+        ("accel", args.accel),
+        ("scale", args.scale),
+        ("exponent", args.exponent),
+        ("offset", args.offset),
+        ("decay_rate", args.decay_rate),
+    ];
+    let mut overrides = Vec::new();
+    for (name, value) in params {
+        let Some(value) = value else {
+            continue;
+        };
+        let keys: Vec<_> = CURVE_KEYS
+            .into_iter()
+            .filter(|key| curve_at(key).is_some_and(|curve| curve.contains_key(name)))
+            .collect();
+        if keys.is_empty() {
+            anyhow::bail!(
+                "--{} doesn't apply to any of the wheel curves, pick one that takes it with --curve",
+                name.replace('_', "-")
+            );
+        }
+        for key in keys {
+            overrides.push(Override::new(format!("{key}.{name}"), f64::from(value)));
+        }
+    }
+    Ok(overrides)
+}
+
+/// Apply the flags that switch features on or set the device, which only ever add to
+/// what the configuration says
+fn apply_flags(config: &mut Config, args: &Args) {
+    let device = &mut config.device;
+    if let Some(path) = &args.device {
+        device.path = Some(path.clone());
+    }
+    device.keyboard_modifiers |= args.keyboard_modifiers;
+    if !args.keyboards.is_empty() {
+        device.keyboards = args.keyboards.clone();
+    }
+
+    let scroll_params = &mut config.scroll;
+    for curve in [&mut scroll_params.vertical, &mut scroll_params.horizontal] {
+        // Classic curves call it `cap`, so this can't go through a key
+        if let Some(max_sens) = args.max_sens {
+            curve.positive.set_max_sens(max_sens);
+            curve.negative.set_max_sens(max_sens);
+        }
+        if let Some(max_sens) = args.max_sens_up {
+            curve.positive.set_max_sens(max_sens);
+        }
+        if let Some(max_sens) = args.max_sens_down {
+            curve.negative.set_max_sens(max_sens);
+        }
+    }
+    scroll_params.drag.enabled |= args.drag_scroll || args.autoscroll;
+    scroll_params.drag.autoscroll |= args.autoscroll;
+    scroll_params.pointer.enabled |= args.pointer_accel;
+    scroll_params.mapping.swap |= args.swap_axes;
+    scroll_params.mapping.invert_vertical |= args.invert_vertical;
+    scroll_params.mapping.invert_horizontal |= args.invert_horizontal;
+    scroll_params.debounce.enabled |= args.debounce_wheel;
+    scroll_params.chatter.enabled |= args.debounce_buttons;
+}
+
+/// Tells the event loop when to reload the configuration: on SIGHUP, or when the file
+/// is written
+struct ConfigWatcher {
+    /// The configuration file, at its default location if `--config` wasn't given
+    path: PathBuf,
+    signals: SignalFd,
+    /// `None` if the file's directory can't be watched, leaving only SIGHUP
+    inotify: Option<Inotify>,
+}
+
+impl ConfigWatcher {
+    fn new(path: PathBuf) -> Result<Self> {
+        // SIGHUP is read from the signalfd instead of being delivered
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGHUP);
+        mask.thread_block().context("Failed to block SIGHUP")?;
+        let signals = SignalFd::with_flags(&mask, SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC)
+            .context("Failed to create signalfd")?;
+
+        // Editors often save by replacing the file, which only its directory sees
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let inotify =
+            Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC).and_then(|inotify| {
+                inotify.add_watch(
+                    dir,
+                    AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+                )?;
+                Ok(inotify)
+            });
+        let inotify = match inotify {
+            Ok(inotify) => {
+                info!("Watching {} for changes", path.display());
+                Some(inotify)
+            }
+            Err(e) => {
+                warn!(
+                    "Can't watch {} for changes ({}), reload with SIGHUP instead",
+                    dir.display(),
+                    e
+                );
+                None
+            }
+        };
+
+        Ok(Self {
+            path,
+            signals,
+            inotify,
+        })
+    }
+
+    /// Drain the pending signals and file events, and say whether any of them calls
+    /// for a reload
+    fn take_reload(&mut self) -> Result<bool> {
+        let mut reload = false;
+        while self
+            .signals
+            .read_signal()
+            .context("Failed to read signal")?
+            .is_some()
+        {
+            info!("Received SIGHUP");
+            reload = true;
+        }
+        if let Some(inotify) = &self.inotify {
+            loop {
+                let events = match inotify.read_events() {
+                    Ok(events) => events,
+                    Err(Errno::EAGAIN) => break,
+                    Err(e) => return Err(e).context("Failed to read file events"),
+                };
+                let file_name = self.path.file_name();
+                if events
+                    .iter()
+                    .any(|event| event.name.as_deref() == file_name)
+                {
+                    info!("{} changed", self.path.display());
+                    reload = true;
+                }
+            }
+        }
+        Ok(reload)
+    }
+}
+
+/// Load the configuration from `path`, or from the default location if there is a file
+/// there. Without either the defaults are used.
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => Path::new(DEFAULT_CONFIG_PATH),
+        None => {
+            info!(
+                "No configuration at {}, using defaults",
+                DEFAULT_CONFIG_PATH
+            );
+            return Ok(Config::default());
+        }
+    };
+    let config = Config::load(path)
+        .with_context(|| format!("Failed to load configuration from {}", path.display()))?;
+    info!("Loaded configuration from {}", path.display());
+    Ok(config)
+}
+
+/// What profiles are matched against, read from the device opened at `path`
+fn device_info(device: &Device, path: &Path) -> DeviceInfo {
+    let input_id = device.input_id();
+    // Compare resolved paths, as `path` may itself be one of the links
+    let node = path.canonicalize().ok();
+    let by_id = std::fs::read_dir("/dev/input/by-id")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| node.is_some() && entry.path().canonicalize().ok() == node)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    DeviceInfo {
+        bus: input_id.bus_type().0,
+        vendor: input_id.vendor(),
+        product: input_id.product(),
+        name: device.name().unwrap_or("Unknown").to_owned(),
+        phys: device.physical_path().map(str::to_owned),
+        by_id,
+    }
+}
+
+fn find_mouse_device(device_path: Option<PathBuf>) -> Result<(Device, PathBuf)> {
     if let Some(path) = device_path {
         info!("Using specified device: {}", path.display());
-        return Device::open(&path).context("Failed to open specified device");
+        let device = Device::open(&path).context("Failed to open specified device")?;
+        return Ok((device, path));
     }
 
     info!("Searching for mouse devices...");
@@ -81,28 +525,22 @@ fn find_mouse_device(device_path: Option<PathBuf>) -> Result<Device> {
 
         // Check if it's a mouse by looking for mouse capabilities
         let events = device.supported_events();
-        if events.contains(EventType::RELATIVE) {
-            if let Some(relative_axes) = device.supported_relative_axes() {
-                if relative_axes.contains(RelativeAxisCode::REL_X)
-                    && relative_axes.contains(RelativeAxisCode::REL_Y)
-                    && relative_axes.contains(RelativeAxisCode::REL_WHEEL)
-                    && relative_axes.contains(RelativeAxisCode::REL_HWHEEL)
-                {
-                    let input_id = device.input_id();
-                    let product = input_id.product();
-                    info!(
-                        "Found mouse device: {} at {} (product: 0x{:04x})",
-                        name,
-                        path.display(),
-                        product
-                    );
-                    match &mut best {
-                        None => best = Some((device, product, path)),
-                        Some((_, best_prod, _)) => {
-                            if product < *best_prod {
-                                best = Some((device, product, path));
-                            }
-                        }
+        if events.contains(EventType::RELATIVE)
+            && device.supported_relative_axes().is_some_and(is_wheel_mouse)
+        {
+            let input_id = device.input_id();
+            let product = input_id.product();
+            info!(
+                "Found mouse device: {} at {} (product: 0x{:04x})",
+                name,
+                path.display(),
+                product
+            );
+            match &mut best {
+                None => best = Some((device, product, path)),
+                Some((_, best_prod, _)) => {
+                    if product < *best_prod {
+                        best = Some((device, product, path));
                     }
                 }
             }
@@ -116,18 +554,81 @@ fn find_mouse_device(device_path: Option<PathBuf>) -> Result<Device> {
             path.display(),
             product_id
         );
-        return Ok(device);
+        return Ok((device, path));
     }
 
     anyhow::bail!("No suitable mouse device found. Please specify a device path with --device")
 }
 
+/// Opens a device without write access, unlike `Device::open`, which takes it when it
+/// can. Keyboards are only listened to, so nothing can be written to them by mistake.
+fn open_read_only(path: &Path) -> std::io::Result<Device> {
+    let file = std::fs::OpenOptions::new().read(true).open(path)?;
+    Device::from_fd(file.into())
+}
+
+fn find_keyboard_devices(device_paths: Vec<PathBuf>) -> Result<Vec<Device>> {
+    if !device_paths.is_empty() {
+        return device_paths
+            .iter()
+            .map(|path| {
+                info!("Using specified keyboard: {}", path.display());
+                open_read_only(path)
+                    .with_context(|| format!("Failed to open keyboard {}", path.display()))
+            })
+            .collect();
+    }
+
+    info!("Searching for keyboard devices...");
+    let mut keyboards = Vec::new();
+
+    let nodes = std::fs::read_dir("/dev/input").context("Failed to list /dev/input")?;
+    for path in nodes.filter_map(|entry| Some(entry.ok()?.path())) {
+        let is_event_node = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"event"));
+        if !is_event_node {
+            continue;
+        }
+        let Ok(device) = open_read_only(&path) else {
+            continue;
+        };
+        let name = device.name().unwrap_or("Unknown");
+
+        // Check if it's a keyboard by looking for modifier and letter keys
+        if let Some(keys) = device.supported_keys()
+            && keys.contains(KeyCode::KEY_LEFTSHIFT)
+            && keys.contains(KeyCode::KEY_LEFTALT)
+            && keys.contains(KeyCode::KEY_A)
+            && name != VIRTUAL_DEVICE_NAME
+        {
+            info!("Found keyboard device: {} at {}", name, path.display());
+            keyboards.push(device);
+        }
+    }
+
+    if keyboards.is_empty() {
+        warn!("No keyboard devices found, keyboard modifiers will have no effect");
+    }
+    Ok(keyboards)
+}
+
+fn use_monotonic_clock(device: &Device) -> Result<()> {
+    let clock_id: libc::c_int = libc::CLOCK_MONOTONIC;
+    // SAFETY: the fd is owned by `device` and stays open for the duration of the call,
+    // and EVIOCSCLOCKID only reads a single int through the pointer.
+    unsafe { eviocsclockid(device.as_raw_fd(), &clock_id) }
+        .context("Failed to switch device to CLOCK_MONOTONIC")?;
+    info!("Switched device timestamps to CLOCK_MONOTONIC");
+    Ok(())
+}
+
 fn create_virtual_mouse(physical_device: &Device) -> Result<VirtualDevice> {
-    let mut builder = VirtualDevice::builder()?.name("Anxious Scroll Daemon");
+    let mut builder = VirtualDevice::builder()?.name(VIRTUAL_DEVICE_NAME);
 
     // Add relative axes (mouse movement and scroll)
     if let Some(relative_axes) = physical_device.supported_relative_axes() {
-        builder = builder.with_relative_axes(&relative_axes)?;
+        builder = builder.with_relative_axes(&virtual_relative_axes(relative_axes))?;
     }
 
     // Add absolute axes (if any) - skip for now as it's complex to set up properly
@@ -135,7 +636,7 @@ fn create_virtual_mouse(physical_device: &Device) -> Result<VirtualDevice> {
 
     // Add keys (mouse buttons)
     if let Some(keys) = physical_device.supported_keys() {
-        builder = builder.with_keys(&keys)?;
+        builder = builder.with_keys(keys)?;
     }
 
     Ok(builder.build()?)
@@ -143,25 +644,129 @@ fn create_virtual_mouse(physical_device: &Device) -> Result<VirtualDevice> {
 
 fn run_pass_through_loop(
     physical_device: &mut Device,
+    keyboards: &mut Vec<Device>,
     virtual_device: &mut VirtualDevice,
-    anxious_params: &AnxiousParams,
-    anxious_state: &mut AnxiousState,
+    config_watcher: &mut ConfigWatcher,
+    reload: impl Fn() -> Result<Config>,
+    scroll_params: &mut ScrollParams<DirectionalCurve<Curve>>,
+    scroll_state: &mut ScrollState,
 ) -> Result<()> {
+    let clock = MonotonicClock;
+    // Wakes the loop for output that isn't driven by input, like kinetic scrolling
+    let timer = TimerFd::new(
+        ClockId::CLOCK_MONOTONIC,
+        TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+    )
+    .context("Failed to create timer")?;
+
     loop {
-        match physical_device.fetch_events() {
-            Ok(events) => {
-                // Process events using the pure function from lib
-                let event_batch = process_events(events, anxious_params, anxious_state);
-
-                // Emit all events in the batch together
-                if !event_batch.is_empty() {
-                    virtual_device.emit(&event_batch)?;
+        match next_deadline(scroll_params, scroll_state) {
+            Some(deadline) => timer.set(
+                Expiration::OneShot(TimeSpec::from(deadline)),
+                TimerSetTimeFlags::TFD_TIMER_ABSTIME,
+            )?,
+            None => timer.unset()?,
+        }
+
+        let (device_ready, timer_ready, reload_ready, keyboards_ready) = {
+            let mut fds = vec![
+                PollFd::new(physical_device.as_fd(), PollFlags::POLLIN),
+                PollFd::new(timer.as_fd(), PollFlags::POLLIN),
+                PollFd::new(config_watcher.signals.as_fd(), PollFlags::POLLIN),
+            ];
+            fds.extend(
+                config_watcher
+                    .inotify
+                    .iter()
+                    .map(|inotify| PollFd::new(inotify.as_fd(), PollFlags::POLLIN)),
+            );
+            let keyboards_start = fds.len();
+            fds.extend(
+                keyboards
+                    .iter()
+                    .map(|keyboard| PollFd::new(keyboard.as_fd(), PollFlags::POLLIN)),
+            );
+            match poll(&mut fds, PollTimeout::NONE) {
+                Ok(_) => {}
+                Err(Errno::EINTR) => continue,
+                Err(e) => return Err(e).context("Failed to poll for events"),
+            }
+            let ready = |fd: &PollFd| fd.revents().is_some_and(|r| !r.is_empty());
+            let keyboards_ready: Vec<bool> = fds[keyboards_start..].iter().map(ready).collect();
+            let reload_ready = fds[2..keyboards_start].iter().any(ready);
+            (
+                ready(&fds[0]),
+                ready(&fds[1]),
+                reload_ready,
+                keyboards_ready,
+            )
+        };
+
+        // Swap the parameters between batches, keeping the grab, the virtual device and
+        // the velocity history
+        if reload_ready && config_watcher.take_reload()? {
+            match reload() {
+                Ok(config) => {
+                    *scroll_params = config.scroll;
+                    scroll_state.reconfigure(scroll_params);
+                    info!("Reloaded configuration, device settings apply after a restart");
                 }
+                Err(e) => error!("Keeping the current configuration: {:#}", e),
             }
-            Err(e) => {
-                error!("Error reading events: {}", e);
-                // Continue the loop to keep trying
-                std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // Update modifier state before the wheel events it applies to
+        let mut ready = keyboards_ready.into_iter();
+        keyboards.retain_mut(|keyboard| {
+            if !ready.next().unwrap_or(false) {
+                return true;
+            }
+            let error = match keyboard.fetch_events() {
+                Ok(events) => {
+                    events.for_each(|event| scroll_state.keyboard.update(&event));
+                    return true;
+                }
+                Err(e) => e,
+            };
+            // Unplugged, stop polling it so the loop doesn't spin on POLLHUP
+            if error.raw_os_error() == Some(libc::ENODEV) {
+                let name = keyboard.name().unwrap_or("Unknown");
+                warn!("Keyboard {} went away", name);
+                return false;
+            }
+            error!("Error reading keyboard events: {}", error);
+            true
+        });
+
+        if device_ready {
+            match physical_device.fetch_events() {
+                Ok(events) => {
+                    // Process events using the pure function from lib
+                    let event_batch = process_events(events, scroll_params, scroll_state);
+
+                    // Emit all events in the batch together
+                    if !event_batch.is_empty() {
+                        virtual_device.emit(&event_batch)?;
+                    }
+                }
+                Err(e) => {
+                    error!("Error reading events: {}", e);
+                    // Continue the loop to keep trying
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+            }
+        }
+
+        if timer_ready {
+            // Drain the expiration count so the timer stops polling as ready
+            if let Err(e) = timer.wait()
+                && e != Errno::EAGAIN
+            {
+                return Err(e).context("Failed to read timer");
+            }
+            let event_batch = tick_events(scroll_params, scroll_state, &clock);
+            if !event_batch.is_empty() {
+                virtual_device.emit(&event_batch)?;
             }
         }
     }
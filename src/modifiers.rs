@@ -0,0 +1,150 @@
+use crate::ScrollCurve;
+use evdev::{EventType, InputEvent, KeyCode};
+use serde::{Deserialize, Serialize};
+
+/// How a held modifier button changes the wheel's sensitivity
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ModifierMode {
+    /// Ignore the curve and scroll at a fixed, usually low, sensitivity
+    Precision { sens: f32 },
+    /// Scale whatever the curve gives by a fixed multiplier
+    Boost { multiplier: f32 },
+}
+
+/// A mouse button that switches the wheel to another mode while it is held
+#[derive(Debug, Clone, PartialEq)]
+pub struct ButtonModifier {
+    /// e.g. `BTN_SIDE`
+    pub button: KeyCode,
+    pub mode: ModifierMode,
+    /// Keep the button's own presses and releases away from the virtual device
+    pub swallow: bool = false,
+}
+
+/// What the keyboard's modifier keys do to the wheel, when keyboards are monitored
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyboardParams {
+    /// Turn the vertical wheel into the horizontal one while Shift is held
+    pub shift_horizontal: bool = true,
+    /// How the wheel scrolls while Alt is held, if it changes at all
+    pub alt: Option<ModifierMode> = Some(ModifierMode::Precision { sens: 0.25 }),
+}
+
+/// Modifier keys held on the monitored keyboards
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyboardState {
+    pub left_shift: bool = false,
+    pub right_shift: bool = false,
+    pub left_alt: bool = false,
+    pub right_alt: bool = false,
+}
+
+impl KeyboardState {
+    /// Follow a key event from any of the monitored keyboards
+    pub fn update(&mut self, event: &InputEvent) {
+        if event.event_type() != EventType::KEY {
+            return;
+        }
+        let key = match KeyCode(event.code()) {
+            KeyCode::KEY_LEFTSHIFT => &mut self.left_shift,
+            KeyCode::KEY_RIGHTSHIFT => &mut self.right_shift,
+            KeyCode::KEY_LEFTALT => &mut self.left_alt,
+            KeyCode::KEY_RIGHTALT => &mut self.right_alt,
+            _ => return,
+        };
+        // 1 is a press and 2 an autorepeat, both mean the key is down
+        *key = event.value() != 0;
+    }
+
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn alt(&self) -> bool {
+        self.left_alt || self.right_alt
+    }
+}
+
+/// A curve with a modifier applied on top
+#[derive(Debug, Clone, Copy)]
+pub struct ModifiedCurve<'a, C: ?Sized> {
+    pub curve: &'a C,
+    pub mode: ModifierMode,
+}
+
+impl<C: ScrollCurve + ?Sized> ScrollCurve for ModifiedCurve<'_, C> {
+    #[inline(always)]
+    fn sensitivity(&self, vel: f32) -> f32 {
+        match self.mode {
+            ModifierMode::Precision { sens } => sens,
+            ModifierMode::Boost { multiplier } => multiplier * self.curve.sensitivity(vel),
+        }
+    }
+
+    #[inline(always)]
+    fn sensitivity_for(&self, value: f32, vel: f32) -> f32 {
+        match self.mode {
+            ModifierMode::Precision { sens } => sens,
+            ModifierMode::Boost { multiplier } => {
+                multiplier * self.curve.sensitivity_for(value, vel)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnxiousParams, DirectionalCurve, LinearCurve};
+
+    #[test]
+    fn test_keyboard_state_follows_either_side() {
+        let key = |code: KeyCode, value| InputEvent::new(EventType::KEY.0, code.0, value);
+        let mut keyboard = KeyboardState::default();
+
+        keyboard.update(&key(KeyCode::KEY_LEFTSHIFT, 1));
+        keyboard.update(&key(KeyCode::KEY_RIGHTSHIFT, 1));
+        keyboard.update(&key(KeyCode::KEY_LEFTSHIFT, 0));
+        assert!(keyboard.shift());
+
+        keyboard.update(&key(KeyCode::KEY_RIGHTALT, 2));
+        keyboard.update(&key(KeyCode::KEY_RIGHTSHIFT, 0));
+        keyboard.update(&key(KeyCode::KEY_A, 1));
+        assert!(!keyboard.shift());
+        assert!(keyboard.alt());
+    }
+
+    #[test]
+    fn test_precision_ignores_velocity() {
+        let curve = AnxiousParams::default();
+        let precise = ModifiedCurve {
+            curve: &curve,
+            mode: ModifierMode::Precision { sens: 0.25 },
+        };
+        assert_eq!(precise.sensitivity(0.0), 0.25);
+        assert_eq!(precise.sensitivity(1000.0), 0.25);
+    }
+
+    #[test]
+    fn test_boost_keeps_direction() {
+        let curve = DirectionalCurve {
+            positive: LinearCurve {
+                base_sens: 1.0,
+                accel: 0.0,
+                max_sens: 1.0,
+            },
+            negative: LinearCurve {
+                base_sens: 2.0,
+                accel: 0.0,
+                max_sens: 2.0,
+            },
+        };
+        let boosted = ModifiedCurve {
+            curve: &curve,
+            mode: ModifierMode::Boost { multiplier: 4.0 },
+        };
+        assert_eq!(boosted.sensitivity_for(120.0, 0.0), 4.0);
+        assert_eq!(boosted.sensitivity_for(-120.0, 0.0), 8.0);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    AnxiousParams, Clock, Curve, MonotonicClock, ScrollCurve, VelocityEstimator, VelocityParams,
+    carry_scaled, elapsed_since,
+};
+use std::time::Duration;
+
+/// Parameters for pointer motion acceleration
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointerParams {
+    pub enabled: bool = false,
+    /// Maps pointer speed, in pixels per millisecond, to a sensitivity multiplier
+    pub curve: Curve = Curve::Logistic(AnxiousParams::new_unchecked(1.0, 2.5, 0.5)),
+}
+
+/// State for tracking pointer velocity over time, separate from the wheels
+#[derive(Debug)]
+pub struct PointerState {
+    /// Monotonic timestamp of the previous motion frame
+    pub prev_time: Duration,
+    /// Motion of the current frame not yet accelerated, (x, y) in pixels
+    pub pending: (i32, i32) = (0, 0),
+    /// Timestamp of the first motion event of the current frame
+    pub pending_time: Option<Duration> = None,
+    /// Sub-pixel part of the previous output that has not been emitted yet, (x, y)
+    pub remainder: (f32, f32) = (0.0, 0.0),
+    /// Turns the stream of motion frames into a pointer speed for the curve
+    pub velocity: VelocityEstimator = VelocityEstimator::new(VelocityParams::Instant),
+}
+
+impl PointerState {
+    pub fn new() -> Self {
+        Self::with_clock(&MonotonicClock)
+    }
+
+    /// Start tracking from the current time of `clock`
+    pub fn with_clock<C: Clock + ?Sized>(clock: &C) -> Self {
+        Self {
+            prev_time: clock.now(),
+            ..
+        }
+    }
+
+    /// Add motion along `x` and `y` from an event stamped `at` to the current frame
+    pub fn add(&mut self, x: i32, y: i32, at: Duration) {
+        self.pending.0 = self.pending.0.saturating_add(x);
+        self.pending.1 = self.pending.1.saturating_add(y);
+        self.pending_time.get_or_insert(at);
+    }
+
+    /// Accelerate the motion of the frame that just ended, if it had any
+    pub fn take_frame(&mut self, params: &PointerParams) -> Option<(i32, i32)> {
+        let at = self.pending_time.take()?;
+        let (x, y) = std::mem::take(&mut self.pending);
+        Some(self.accelerate(params, x as f32, y as f32, at))
+    }
+
+    /// Scale a motion of (`x`, `y`) pixels at `timestamp` by the sensitivity the curve
+    /// gives for the speed of the whole motion vector
+    pub fn accelerate(
+        &mut self,
+        params: &PointerParams,
+        x: f32,
+        y: f32,
+        timestamp: Duration,
+    ) -> (i32, i32) {
+        let elapsed_time = elapsed_since(&mut self.prev_time, timestamp);
+
+        let distance = x.hypot(y);
+        let vel = self
+            .velocity
+            .update(distance, elapsed_time.as_secs_f32() * 1000.0);
+        let sens = params.curve.sensitivity(vel);
+
+        (
+            carry_scaled(x, sens, &mut self.remainder.0),
+            carry_scaled(y, sens, &mut self.remainder.1),
+        )
+    }
+}
+
+impl Default for PointerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LinearCurve, MockClock};
+
+    const START: Duration = Duration::from_secs(10);
+
+    fn params() -> PointerParams {
+        PointerParams { enabled: true, .. }
+    }
+
+    /// Feed one frame of motion every `interval_ms`, returning the accelerated frames
+    fn run(params: &PointerParams, frames: &[(i32, i32)], interval_ms: u64) -> Vec<(i32, i32)> {
+        let clock = MockClock::new(START);
+        let mut pointer = PointerState::with_clock(&clock);
+        frames
+            .iter()
+            .map(|&(x, y)| {
+                clock.advance(Duration::from_millis(interval_ms));
+                pointer.add(x, y, clock.now());
+                pointer.take_frame(params).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fast_motion_is_accelerated() {
+        let params = params();
+        let slow = run(&params, &[(1, 0); 10], 100);
+        let fast = run(&params, &[(40, 0); 10], 1);
+
+        assert!(slow.iter().all(|&(x, y)| x == 1 && y == 0));
+        assert!(fast.iter().all(|&(x, _)| x > 80));
+    }
+
+    #[test]
+    fn test_speed_comes_from_the_whole_vector() {
+        let params = PointerParams {
+            curve: Curve::Linear(LinearCurve {
+                base_sens: 1.0,
+                accel: 0.1,
+                max_sens: 10.0,
+            }),
+            ..params()
+        };
+        // 30 px straight or 18 and 24 px diagonally both travel 30 px, so both get
+        // a sensitivity of 1 + 0.1 * 30 / 1 ms = 4
+        assert_eq!(run(&params, &[(30, 0)], 1), [(120, 0)]);
+        assert_eq!(run(&params, &[(18, -24)], 1), [(72, -96)]);
+    }
+
+    #[test]
+    fn test_sub_pixel_motion_is_carried() {
+        let params = PointerParams {
+            curve: Curve::Linear(LinearCurve {
+                base_sens: 0.5,
+                accel: 0.0,
+                max_sens: 0.5,
+            }),
+            ..params()
+        };
+        let frames = run(&params, &[(1, 1), (1, 1), (1, -1), (1, -1)], 8);
+        assert_eq!(frames, [(0, 0), (1, 1), (0, 0), (1, -1)]);
+    }
+
+    #[test]
+    fn test_empty_frame_yields_nothing() {
+        let mut pointer = PointerState::with_clock(&MockClock::new(START));
+        assert_eq!(pointer.take_frame(&params()), None);
+    }
+}
@@ -0,0 +1,145 @@
+use regex::Regex;
+use std::path::Path;
+
+/// What a profile can tell devices apart by, as read from the device
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceInfo {
+    /// `BUS_*` code of the `input_id`, e.g. 3 for USB
+    pub bus: u16 = 0,
+    pub vendor: u16 = 0,
+    pub product: u16 = 0,
+    pub name: String = String::new(),
+    /// Physical path reported by the driver, e.g. `usb-0000:00:14.0-2/input0`
+    pub phys: Option<String> = None,
+    /// Names of the `/dev/input/by-id` symlinks pointing at the device
+    pub by_id: Vec<String> = Vec::new(),
+}
+
+/// A regex matched against the device name, compared by its source
+#[derive(Debug, Clone)]
+pub struct NamePattern(pub Regex);
+
+impl PartialEq for NamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+/// Rules a device has to pass for a profile to apply. Every rule that is set has to
+/// match, and rules left unset match anything.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DeviceMatch {
+    pub bus: Option<u16> = None,
+    pub vendor: Option<u16> = None,
+    pub product: Option<u16> = None,
+    /// Searched for anywhere in the name, anchor it with `^` and `$` to match all of it
+    pub name: Option<NamePattern> = None,
+    /// Compared with the whole physical path
+    pub phys: Option<String> = None,
+    /// Name of a `/dev/input/by-id` symlink, with or without the directory
+    pub by_id: Option<String> = None,
+}
+
+impl DeviceMatch {
+    /// Whether no rule is set, which would match every device
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        let by_id = self
+            .by_id
+            .as_deref()
+            .map(|link| Path::new(link).file_name().unwrap_or_default());
+        self.bus.is_none_or(|bus| bus == device.bus)
+            && self.vendor.is_none_or(|vendor| vendor == device.vendor)
+            && self.product.is_none_or(|product| product == device.product)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|pattern| pattern.0.is_match(&device.name))
+            && self
+                .phys
+                .as_ref()
+                .is_none_or(|phys| device.phys.as_ref() == Some(phys))
+            && by_id.is_none_or(|link| device.by_id.iter().any(|name| link == name.as_str()))
+    }
+}
+
+/// Settings for the devices that pass `matches`, layered over the top-level ones
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    /// Shown in the log and in errors
+    pub name: String,
+    pub matches: DeviceMatch = DeviceMatch { .. },
+    /// A partial `[scroll]` table. Keys left out keep their top-level value, and a table
+    /// with a `type`, such as a curve, replaces the top-level one as a whole.
+    pub scroll: toml::Table,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mouse() -> DeviceInfo {
+        DeviceInfo {
+            bus: 3,
+            vendor: 0x046d,
+            product: 0xc08b,
+            name: "Logitech G502 HERO Gaming Mouse".to_owned(),
+            phys: Some("usb-0000:00:14.0-2/input0".to_owned()),
+            by_id: vec!["usb-Logitech_G502_HERO_Gaming_Mouse_0E6D395D3333-event-mouse".to_owned()],
+        }
+    }
+
+    #[test]
+    fn test_every_rule_has_to_match() {
+        let rules = DeviceMatch {
+            vendor: Some(0x046d),
+            product: Some(0xc08b),
+            ..
+        };
+        assert!(rules.matches(&mouse()));
+
+        let other = DeviceInfo {
+            product: 0xc52b,
+            ..mouse()
+        };
+        assert!(!rules.matches(&other));
+
+        assert!(DeviceMatch::default().is_empty());
+        assert!(!rules.is_empty());
+    }
+
+    #[test]
+    fn test_name_phys_and_by_id_rules() {
+        let name = |pattern: &str| DeviceMatch {
+            name: Some(NamePattern(Regex::new(pattern).unwrap())),
+            ..
+        };
+        assert!(name("G502").matches(&mouse()));
+        assert!(!name("^G502$").matches(&mouse()));
+
+        let phys = DeviceMatch {
+            phys: Some("usb-0000:00:14.0-2/input0".to_owned()),
+            ..
+        };
+        assert!(phys.matches(&mouse()));
+        let no_phys = DeviceInfo {
+            phys: None,
+            ..mouse()
+        };
+        assert!(!phys.matches(&no_phys));
+
+        for link in [
+            "usb-Logitech_G502_HERO_Gaming_Mouse_0E6D395D3333-event-mouse",
+            "/dev/input/by-id/usb-Logitech_G502_HERO_Gaming_Mouse_0E6D395D3333-event-mouse",
+        ] {
+            let by_id = DeviceMatch {
+                by_id: Some(link.to_owned()),
+                ..
+            };
+            assert!(by_id.matches(&mouse()), "{link}");
+        }
+    }
+}
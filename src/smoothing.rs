@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+/// Parameters for spreading large accelerated deltas over several frames
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SmoothingParams {
+    pub enabled: bool = false,
+    /// Outputs up to this many hi-res units are emitted at once
+    pub threshold: i32 = 240,
+    /// Time a large output is spread over
+    pub duration: Duration = Duration::from_millis(60),
+    /// Time between two smoothed events
+    pub interval: Duration = Duration::from_millis(1),
+}
+
+/// Scroll output of one axis waiting to be spread over the coming frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct Smoother {
+    /// Signed hi-res units not yet emitted
+    pub pending: f32,
+    /// Monotonic time of the last emission or submission
+    pub last: Duration,
+    /// Monotonic time by which everything pending has been emitted
+    pub until: Duration,
+}
+
+impl Smoother {
+    pub const fn new() -> Self {
+        Self {
+            pending: 0.0,
+            last: Duration::ZERO,
+            until: Duration::ZERO,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending == 0.0
+    }
+
+    /// Take an accelerated output produced at `at` and return the part to emit right
+    /// away. Whatever was still pending is caught up to `at` first, then the rest is
+    /// spread together with `value` over the next `duration`.
+    pub fn submit(&mut self, params: &SmoothingParams, value: i32, at: Duration) -> i32 {
+        if !params.enabled {
+            return value;
+        }
+        // A reversal cancels whatever was left of the previous direction
+        if self.pending * (value as f32) < 0.0 {
+            self.pending = 0.0;
+        }
+        if self.is_idle() && value.abs() <= params.threshold {
+            return value;
+        }
+
+        let due = self.drain(params, at);
+        self.pending += value as f32;
+        self.last = at;
+        self.until = at + params.duration;
+        due
+    }
+
+    /// When the next smoothed event is due
+    pub fn deadline(&self, params: &SmoothingParams) -> Option<Duration> {
+        if self.is_idle() {
+            None
+        } else {
+            Some((self.last + params.interval).min(self.until))
+        }
+    }
+
+    /// Emit the share of the pending output that is due by `now`, in whole units
+    pub fn drain(&mut self, params: &SmoothingParams, now: Duration) -> i32 {
+        if self.is_idle() || now <= self.last {
+            return 0;
+        }
+        if now >= self.until || !params.enabled {
+            let rest = self.pending.round();
+            self.pending = 0.0;
+            return rest as i32;
+        }
+
+        // Spread what is left evenly over the time that is left
+        let share = (now - self.last).as_secs_f32() / (self.until - self.last).as_secs_f32();
+        let amount = (self.pending * share).trunc();
+        self.pending -= amount;
+        self.last = now;
+        amount as i32
+    }
+}
+
+impl Default for Smoother {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> SmoothingParams {
+        SmoothingParams { enabled: true, .. }
+    }
+
+    /// Drain `smoother` every interval until it is idle, returning (time, amount) pairs
+    fn drain_all(smoother: &mut Smoother, params: &SmoothingParams) -> Vec<(Duration, i32)> {
+        let mut frames = Vec::new();
+        while let Some(deadline) = smoother.deadline(params) {
+            frames.push((deadline, smoother.drain(params, deadline)));
+            assert!(frames.len() < 10_000, "smoother never finished");
+        }
+        frames
+    }
+
+    #[test]
+    fn test_small_outputs_pass_through() {
+        let mut smoother = Smoother::new();
+        assert_eq!(smoother.submit(&params(), 200, Duration::ZERO), 200);
+        assert!(smoother.is_idle());
+
+        // Nothing is smoothed when disabled
+        let disabled = SmoothingParams::default();
+        assert_eq!(smoother.submit(&disabled, 1800, Duration::ZERO), 1800);
+    }
+
+    #[test]
+    fn test_large_output_is_spread_over_duration() {
+        let params = params();
+        let start = Duration::from_secs(10);
+        let mut smoother = Smoother::new();
+
+        assert_eq!(smoother.submit(&params, 1800, start), 0);
+        let frames = drain_all(&mut smoother, &params);
+
+        assert_eq!(frames.len(), 60);
+        assert_eq!(frames.last().unwrap().0, start + params.duration);
+        assert_eq!(frames.iter().map(|(_, amount)| amount).sum::<i32>(), 1800);
+        assert!(frames.iter().all(|&(_, amount)| amount == 30));
+    }
+
+    #[test]
+    fn test_new_output_is_spread_with_the_rest() {
+        let params = params();
+        let start = Duration::from_secs(10);
+        let mut smoother = Smoother::new();
+        smoother.submit(&params, 1200, start);
+
+        // Half way through, nobody drained the smoother: the overdue half comes out as
+        // soon as the next event arrives, and the rest joins the new output
+        let due = smoother.submit(&params, 600, start + Duration::from_millis(30));
+        assert_eq!(due, 600);
+        assert_eq!(smoother.pending, 1200.0);
+        assert_eq!(smoother.until, start + Duration::from_millis(90));
+
+        let frames = drain_all(&mut smoother, &params);
+        assert_eq!(frames.len(), 60);
+        assert_eq!(frames.iter().map(|(_, amount)| amount).sum::<i32>(), 1200);
+    }
+
+    #[test]
+    fn test_reversal_cancels_pending() {
+        let params = params();
+        let mut smoother = Smoother::new();
+        smoother.submit(&params, 1800, Duration::ZERO);
+        smoother.drain(&params, Duration::from_millis(10));
+
+        assert_eq!(
+            smoother.submit(&params, -120, Duration::from_millis(11)),
+            -120
+        );
+        assert!(smoother.is_idle());
+    }
+}
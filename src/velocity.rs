@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Shortest interval the smoothing estimators will divide by, in milliseconds.
+/// Bursty receivers can deliver several events with (almost) identical timestamps.
+const MIN_INTERVAL_MS: f32 = crate::MIN_INTERVAL.as_secs_f32() * 1000.0;
+
+/// Upper bound on the samples kept by the windowed estimator
+const WINDOW_CAPACITY: usize = 64;
+
+/// Which velocity estimator to use, and how to tune it
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum VelocityParams {
+    /// Distance of the current event over the time since the previous one.
+    /// Cheapest, but very noisy with receivers that deliver events in bursts.
+    #[default]
+    Instant,
+    /// Total distance over the total time of the events inside a sliding time window
+    Window {
+        #[serde(with = "crate::config::millis")]
+        window: Duration,
+    },
+    /// Time-aware exponential moving average. Each event pulls the estimate towards
+    /// its instantaneous velocity by `1 - e^(-dt / time_constant)`.
+    Ema {
+        #[serde(with = "crate::config::millis")]
+        time_constant: Duration,
+    },
+    /// One-dimensional Kalman filter on a constant-velocity model. Events that arrive
+    /// very close together are trusted less than those spread over a longer interval.
+    Kalman {
+        /// How much the true velocity is expected to drift per millisecond
+        process_noise: f32,
+        /// Noise of a velocity measurement taken over one millisecond
+        measurement_noise: f32,
+    },
+}
+
+/// When the scroll velocity a wheel has built up is forgotten, so the first tick of a new
+/// gesture is not accelerated by the one before it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResetPolicy {
+    /// Start over when the wheel changes direction. Without this a quick correction
+    /// after a fast scroll is accelerated just as much and overshoots.
+    pub on_reversal: bool = true,
+    /// Start over once the wheel has been idle for this long
+    pub idle_timeout: Option<Duration> = None,
+    /// Time constant the accumulated velocity decays with between two events.
+    /// Has no effect on [`VelocityParams::Instant`], which accumulates nothing.
+    pub decay: Option<Duration> = None,
+}
+
+#[derive(Debug)]
+enum EstimatorState {
+    Instant,
+    /// (interval in ms, distance) of the most recent events, oldest first
+    Window(VecDeque<(f32, f32)>),
+    Ema {
+        vel: f32,
+    },
+    /// (velocity, variance) once the first event has been seen
+    Kalman(Option<(f32, f32)>),
+}
+
+/// Estimates scroll velocity (units per millisecond) from a stream of events
+#[derive(Debug)]
+pub struct VelocityEstimator {
+    params: VelocityParams,
+    state: EstimatorState,
+}
+
+impl VelocityEstimator {
+    pub const fn new(params: VelocityParams) -> Self {
+        let state = match params {
+            VelocityParams::Instant => EstimatorState::Instant,
+            VelocityParams::Window { .. } => EstimatorState::Window(VecDeque::new()),
+            VelocityParams::Ema { .. } => EstimatorState::Ema { vel: 0.0 },
+            VelocityParams::Kalman { .. } => EstimatorState::Kalman(None),
+        };
+        Self { params, state }
+    }
+
+    pub fn params(&self) -> &VelocityParams {
+        &self.params
+    }
+
+    /// Forget all history, as if no event had been seen yet
+    pub fn reset(&mut self) {
+        *self = Self::new(self.params);
+    }
+
+    /// Scale the accumulated history by `factor`, so the next estimate starts out
+    /// that much slower
+    pub fn decay(&mut self, factor: f32) {
+        match &mut self.state {
+            EstimatorState::Instant => {}
+            EstimatorState::Window(samples) => {
+                for (_, distance) in samples {
+                    *distance *= factor;
+                }
+            }
+            EstimatorState::Ema { vel } => *vel *= factor,
+            EstimatorState::Kalman(filter) => {
+                if let Some((vel, _)) = filter {
+                    *vel *= factor;
+                }
+            }
+        }
+    }
+
+    /// Feed one event that moved `distance` units, `elapsed_ms` after the previous
+    /// event, and return the updated velocity estimate
+    pub fn update(&mut self, distance: f32, elapsed_ms: f32) -> f32 {
+        match (&self.params, &mut self.state) {
+            (VelocityParams::Instant, EstimatorState::Instant) => distance / elapsed_ms,
+            (VelocityParams::Window { window }, EstimatorState::Window(samples)) => {
+                let window_ms = window.as_secs_f32() * 1000.0;
+                if samples.len() == WINDOW_CAPACITY {
+                    samples.pop_front();
+                }
+                samples.push_back((elapsed_ms, distance));
+
+                // Keep the shortest run of recent events that still covers the window
+                let mut span: f32 = samples.iter().map(|(dt, _)| dt).sum();
+                while let Some(&(oldest_dt, _)) = samples.front() {
+                    if samples.len() == 1 || span - oldest_dt < window_ms {
+                        break;
+                    }
+                    span -= oldest_dt;
+                    samples.pop_front();
+                }
+
+                let total: f32 = samples.iter().map(|(_, d)| d).sum();
+                total / span.max(MIN_INTERVAL_MS)
+            }
+            (VelocityParams::Ema { time_constant }, EstimatorState::Ema { vel }) => {
+                let tau_ms = time_constant.as_secs_f32() * 1000.0;
+                let decay = (-elapsed_ms / tau_ms).exp();
+                // (1 - decay) * distance / dt, which tends to distance / tau as dt -> 0
+                let gain = if elapsed_ms > 0.0 {
+                    (1.0 - decay) / elapsed_ms
+                } else {
+                    1.0 / tau_ms
+                };
+                *vel = *vel * decay + gain * distance;
+                *vel
+            }
+            (
+                VelocityParams::Kalman {
+                    process_noise,
+                    measurement_noise,
+                },
+                EstimatorState::Kalman(filter),
+            ) => {
+                let dt = elapsed_ms.max(MIN_INTERVAL_MS);
+                let measurement = distance / dt;
+                // Measurements taken over short intervals are less reliable
+                let measurement_variance = measurement_noise / dt;
+
+                match filter {
+                    // The first measurement has nothing to be blended with
+                    None => {
+                        *filter = Some((measurement, measurement_variance));
+                        measurement
+                    }
+                    Some((vel, variance)) => {
+                        // Predict: the velocity may have drifted since the last event
+                        *variance += process_noise * dt;
+                        // Update: blend in the measurement by relative confidence
+                        let gain = *variance / (*variance + measurement_variance);
+                        *vel += gain * (measurement - *vel);
+                        *variance *= 1.0 - gain;
+                        *vel
+                    }
+                }
+            }
+            _ => unreachable!("estimator state always matches its params"),
+        }
+    }
+}
+
+impl Default for VelocityEstimator {
+    fn default() -> Self {
+        Self::new(VelocityParams::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A steady 15 units/ms scroll (one 120 unit notch every 8 ms), as delivered by a
+    /// receiver that batches pairs of notches 0.1 ms apart every 16 ms
+    fn bursty_trace() -> Vec<(f32, f32)> {
+        (0..200)
+            .map(|i| {
+                if i % 2 == 0 {
+                    (15.9, 120.0)
+                } else {
+                    (0.1, 120.0)
+                }
+            })
+            .collect()
+    }
+
+    /// The same scroll delivered at a perfectly even rate
+    fn steady_trace() -> Vec<(f32, f32)> {
+        (0..200).map(|_| (8.0, 120.0)).collect()
+    }
+
+    fn run(params: VelocityParams, trace: &[(f32, f32)]) -> Vec<f32> {
+        let mut estimator = VelocityEstimator::new(params);
+        trace
+            .iter()
+            .map(|&(dt, distance)| estimator.update(distance, dt))
+            .collect()
+    }
+
+    /// Largest relative deviation from the true velocity once the estimator settled
+    fn worst_error(estimates: &[f32], truth: f32) -> f32 {
+        estimates[estimates.len() / 2..]
+            .iter()
+            .map(|v| (v - truth).abs() / truth)
+            .fold(0.0, f32::max)
+    }
+
+    fn smoothing_params() -> [VelocityParams; 3] {
+        [
+            VelocityParams::Window {
+                window: Duration::from_millis(50),
+            },
+            VelocityParams::Ema {
+                time_constant: Duration::from_millis(30),
+            },
+            VelocityParams::Kalman {
+                process_noise: 0.01,
+                measurement_noise: 100.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_instant_is_noisy_on_bursts() {
+        let estimates = run(VelocityParams::Instant, &bursty_trace());
+        // The 0.1 ms half of each pair reads as 80x the real speed
+        assert!(worst_error(&estimates, 15.0) > 50.0);
+    }
+
+    #[test]
+    fn test_smoothing_estimators_handle_bursts() {
+        for params in smoothing_params() {
+            let instant = worst_error(&run(VelocityParams::Instant, &bursty_trace()), 15.0);
+            let smoothed = worst_error(&run(params, &bursty_trace()), 15.0);
+            assert!(smoothed < 0.25, "{params:?} was off by {smoothed}");
+            assert!(
+                smoothed * 100.0 < instant,
+                "{params:?} did not beat instant"
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimators_agree_on_steady_scroll() {
+        for params in smoothing_params() {
+            let error = worst_error(&run(params, &steady_trace()), 15.0);
+            assert!(error < 0.05, "{params:?} was off by {error}");
+        }
+        let error = worst_error(&run(VelocityParams::Instant, &steady_trace()), 15.0);
+        assert!(error < 1e-6);
+    }
+
+    #[test]
+    fn test_window_forgets_old_events() {
+        let mut estimator = VelocityEstimator::new(VelocityParams::Window {
+            window: Duration::from_millis(50),
+        });
+        for _ in 0..20 {
+            estimator.update(120.0, 2.0);
+        }
+        // A single slow tick after a pause must not inherit the earlier flick
+        let vel = estimator.update(120.0, 1000.0);
+        assert!((vel - 0.12).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_zero_interval_stays_finite() {
+        for params in smoothing_params() {
+            let mut estimator = VelocityEstimator::new(params);
+            let vel = estimator.update(120.0, 0.0);
+            assert!(vel.is_finite(), "{params:?} returned {vel}");
+        }
+    }
+
+    #[test]
+    fn test_decay_slows_accumulated_history() {
+        for params in smoothing_params() {
+            let mut decayed = VelocityEstimator::new(params);
+            let mut kept = VelocityEstimator::new(params);
+            for _ in 0..20 {
+                decayed.update(120.0, 8.0);
+                kept.update(120.0, 8.0);
+            }
+            decayed.decay(0.1);
+            let slow = decayed.update(120.0, 8.0);
+            let fast = kept.update(120.0, 8.0);
+            assert!(
+                slow < fast * 0.5,
+                "{params:?} did not decay: {slow} vs {fast}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reset_clears_history() {
+        let mut estimator = VelocityEstimator::new(VelocityParams::Ema {
+            time_constant: Duration::from_millis(30),
+        });
+        for _ in 0..20 {
+            estimator.update(120.0, 8.0);
+        }
+        estimator.reset();
+        let vel = estimator.update(120.0, 1000.0);
+        assert!(vel < 0.2);
+    }
+}